@@ -0,0 +1,305 @@
+use crate::modals::{Analyzer, AnalyzerAction, DuNode, FileManager, PopupType};
+use crate::utils::move_to_trash;
+use std::fs::{self, Metadata};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tokio::sync::oneshot;
+
+fn node_from(path: PathBuf, metadata: &Metadata) -> DuNode {
+    // `blocks` is always in 512-byte units, whatever the filesystem block size.
+    DuNode::new(
+        path,
+        metadata.is_dir(),
+        metadata.len(),
+        metadata.blocks() * 512,
+    )
+}
+
+// Symlinks are counted, not followed; other filesystems are not entered.
+fn scan_node(path: PathBuf, metadata: &Metadata, device: u64, scanned: &AtomicU64) -> DuNode {
+    let mut node = node_from(path, metadata);
+    if !metadata.is_dir() {
+        scanned.fetch_add(1, Ordering::Relaxed);
+        return node;
+    }
+    if metadata.dev() != device {
+        return node;
+    }
+    if let Ok(entries) = fs::read_dir(node.path()) {
+        for entry in entries.flatten() {
+            if let Ok(child) = entry.metadata() {
+                node.push(scan_node(entry.path(), &child, device, scanned));
+            }
+        }
+    }
+    node
+}
+
+// The top-level entries are shared out between one thread per core.
+fn scan_tree(root: &Path, scanned: &AtomicU64) -> Option<DuNode> {
+    let metadata = fs::symlink_metadata(root).ok()?;
+    let device = metadata.dev();
+    let mut node = node_from(root.to_path_buf(), &metadata);
+
+    let queue: Vec<(PathBuf, Metadata)> = fs::read_dir(root)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| Some((entry.path(), entry.metadata().ok()?)))
+        .collect();
+    let queue = Mutex::new(queue);
+    let results = Mutex::new(Vec::new());
+    let workers = thread::available_parallelism().map_or(4, |n| n.get());
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            // The guard is consumed by the closure, so the lock is not held while scanning.
+            scope.spawn(|| {
+                while let Some((path, metadata)) = queue.lock().ok().and_then(|mut q| q.pop()) {
+                    let child = scan_node(path, &metadata, device, scanned);
+                    if let Ok(mut results) = results.lock() {
+                        results.push(child);
+                    }
+                }
+            });
+        }
+    });
+
+    for child in results.into_inner().unwrap_or_default() {
+        node.push(child);
+    }
+    Some(node)
+}
+
+fn size_of(node: &DuNode, use_disk: bool) -> u64 {
+    if use_disk {
+        node.disk()
+    } else {
+        node.apparent()
+    }
+}
+
+fn sort_tree(node: &mut DuNode, use_disk: bool) {
+    node.children_mut()
+        .sort_by_key(|child| std::cmp::Reverse(size_of(child, use_disk)));
+    for child in node.children_mut() {
+        sort_tree(child, use_disk);
+    }
+}
+
+impl DuNode {
+    // Walks down by path components; nodes are looked up by path so re-sorting never
+    // invalidates where the view is.
+    pub fn find(&self, path: &Path) -> Option<&DuNode> {
+        if path == self.path() {
+            return Some(self);
+        }
+        self.children()
+            .iter()
+            .find(|child| path.starts_with(child.path()))
+            .and_then(|child| child.find(path))
+    }
+
+    // Removes `target` and takes its totals off every directory above it.
+    fn remove(&mut self, target: &Path, use_disk: bool) -> Option<DuNode> {
+        let index = self
+            .children()
+            .iter()
+            .position(|child| target.starts_with(child.path()))?;
+        let removed = if self.children()[index].path() == target {
+            self.children_mut().remove(index)
+        } else {
+            self.children_mut()[index].remove(target, use_disk)?
+        };
+        self.subtract(&removed);
+        self.children_mut()
+            .sort_by_key(|child| std::cmp::Reverse(size_of(child, use_disk)));
+        Some(removed)
+    }
+}
+
+impl Analyzer {
+    pub fn current_node(&self) -> Option<&DuNode> {
+        self.root()?.find(self.current())
+    }
+
+    pub fn hovered_node(&self) -> Option<&DuNode> {
+        let index = self.selection().selected()?;
+        self.current_node()?.children().get(index)
+    }
+
+    pub fn size_of(&self, node: &DuNode) -> u64 {
+        size_of(node, self.use_disk())
+    }
+}
+
+impl FileManager {
+    pub fn start_analyzer(&mut self) {
+        let root = self.current_path().clone();
+        let scanned = Arc::new(AtomicU64::new(0));
+        let (tx, rx) = oneshot::channel();
+
+        let counter = scanned.clone();
+        let scan_root = root.clone();
+        tokio::task::spawn_blocking(move || {
+            if let Some(tree) = scan_tree(&scan_root, &counter) {
+                let _ = tx.send(tree);
+            }
+        });
+
+        self.set_analyzer(Some(Analyzer::new(root, scanned, rx)));
+        self.set_popup(PopupType::Analyzer);
+    }
+
+    pub fn finish_analyzer(&mut self, tree: Result<DuNode, oneshot::error::RecvError>) {
+        let Some(analyzer) = self.analyzer_mut() else {
+            return;
+        };
+        analyzer.set_receiver(None);
+        match tree {
+            Ok(mut tree) => {
+                sort_tree(&mut tree, analyzer.use_disk());
+                analyzer.set_root(Some(tree));
+            }
+            Err(_) => self.show_notification("Failed to scan the directory".to_string()),
+        }
+    }
+
+    pub fn is_scanning(&self) -> bool {
+        self.analyzer()
+            .is_some_and(|analyzer| analyzer.receiver().is_some())
+    }
+
+    pub fn analyzer_navigate(&mut self, down: bool) {
+        let Some(analyzer) = self.analyzer_mut() else {
+            return;
+        };
+        let len = analyzer
+            .current_node()
+            .map_or(0, |node| node.children().len());
+        if len == 0 {
+            return;
+        }
+        let current = analyzer.selection().selected().unwrap_or(0);
+        let next = if down {
+            (current + 1) % len
+        } else {
+            (current + len - 1) % len
+        };
+        analyzer.selection_mut().select(Some(next));
+    }
+
+    pub fn analyzer_enter(&mut self) {
+        let Some(analyzer) = self.analyzer_mut() else {
+            return;
+        };
+        if let Some(path) = analyzer
+            .hovered_node()
+            .filter(|node| node.is_dir())
+            .map(|node| node.path().clone())
+        {
+            analyzer.set_current(path);
+            analyzer.selection_mut().select(Some(0));
+        }
+    }
+
+    // Stops at the directory the scan started from.
+    pub fn analyzer_leave(&mut self) {
+        let Some(analyzer) = self.analyzer_mut() else {
+            return;
+        };
+        let Some(root) = analyzer.root().map(|root| root.path().clone()) else {
+            return;
+        };
+        let current = analyzer.current().clone();
+        if current == root {
+            return;
+        }
+        let Some(parent) = current.parent().map(Path::to_path_buf) else {
+            return;
+        };
+        analyzer.set_current(parent);
+        let index = analyzer
+            .current_node()
+            .and_then(|node| node.children().iter().position(|c| c.path() == &current));
+        analyzer.selection_mut().select(Some(index.unwrap_or(0)));
+    }
+
+    pub fn analyzer_toggle_size(&mut self) {
+        if let Some(analyzer) = self.analyzer_mut() {
+            let use_disk = !analyzer.use_disk();
+            analyzer.set_use_disk(use_disk);
+            if let Some(root) = analyzer.root_mut() {
+                sort_tree(root, use_disk);
+            }
+        }
+    }
+
+    pub fn analyzer_confirm(&mut self, action: AnalyzerAction) {
+        if !self.ensure_writable() {
+            return;
+        }
+        if let Some(analyzer) = self.analyzer_mut() {
+            if analyzer.hovered_node().is_some() {
+                analyzer.set_confirm(Some(action));
+            }
+        }
+    }
+
+    pub fn analyzer_cancel_confirm(&mut self) {
+        if let Some(analyzer) = self.analyzer_mut() {
+            analyzer.set_confirm(None);
+        }
+    }
+
+    pub async fn analyzer_apply(&mut self) {
+        let Some(analyzer) = self.analyzer_mut() else {
+            return;
+        };
+        let action = analyzer.confirm();
+        analyzer.set_confirm(None);
+        let Some(node) = analyzer.hovered_node() else {
+            return;
+        };
+        let (path, is_dir) = (node.path().clone(), node.is_dir());
+
+        let result = match action {
+            Some(AnalyzerAction::Delete) if is_dir => tokio::fs::remove_dir_all(&path).await,
+            Some(AnalyzerAction::Delete) => tokio::fs::remove_file(&path).await,
+            Some(AnalyzerAction::Trash) => move_to_trash(&path),
+            None => return,
+        };
+        if let Err(e) = result {
+            self.show_notification(format!("Failed to remove {}: {e}", path.display()));
+            return;
+        }
+
+        self.invalidate_dir_sizes(&path);
+        if let Some(analyzer) = self.analyzer_mut() {
+            let use_disk = analyzer.use_disk();
+            if let Some(root) = analyzer.root_mut() {
+                root.remove(&path, use_disk);
+            }
+            let len = analyzer
+                .current_node()
+                .map_or(0, |node| node.children().len());
+            let index = analyzer.selection().selected().unwrap_or(0);
+            analyzer
+                .selection_mut()
+                .select(Some(index.min(len.saturating_sub(1))));
+            analyzer.set_changed(true);
+        }
+    }
+
+    pub async fn close_analyzer(&mut self) {
+        self.set_popup(PopupType::None);
+        if self
+            .take_analyzer()
+            .is_some_and(|analyzer| analyzer.changed())
+        {
+            self.reload_current_directory().await;
+        }
+    }
+}
@@ -0,0 +1,674 @@
+use crate::modals::{
+    CaseConversion, FileManager, InteractionMode, PatternField, PatternRename, PopupType,
+};
+use crate::terminal::editor_command;
+use regex::{Captures, Regex};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+// Stands in for a `{n}` token while the regex expands its own `$1` style references.
+const SEQUENCE_MARK: char = '\u{E000}';
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenameStatus {
+    Unchanged,
+    Renamed,
+    Invalid,
+    Collision,
+    NotUtf8,
+}
+
+#[derive(Debug, Clone)]
+pub struct RenamePreviewRow {
+    pub source: PathBuf,
+    pub new_name: String,
+    pub status: RenameStatus,
+}
+
+// Names are edited as text, so a name that isn't UTF-8 shows up with U+FFFD in it. Leaving
+// that text as it was keeps the original name instead of renaming to the mangled one.
+fn rename_target(src: &Path, name: &str) -> PathBuf {
+    match src.file_name() {
+        Some(original) if original.to_string_lossy() == name => src.to_path_buf(),
+        _ => src.with_file_name(name),
+    }
+}
+
+// Checks edited names against their sources and returns the (old, new) pairs that change.
+pub fn plan_renames(
+    sources: &[PathBuf],
+    new_names: &[String],
+) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+    if new_names.len() != sources.len() {
+        return Err(format!(
+            "Expected {} names, found {}",
+            sources.len(),
+            new_names.len()
+        ));
+    }
+
+    let mut plan = Vec::new();
+    let mut targets = HashSet::new();
+    for (src, name) in sources.iter().zip(new_names) {
+        if name.is_empty() || name.contains('/') || name == "." || name == ".." {
+            return Err(format!("Invalid name \"{name}\""));
+        }
+        let dst = rename_target(src, name);
+        if !targets.insert(dst.clone()) {
+            return Err(format!("\"{name}\" is used more than once"));
+        }
+        if dst != *src {
+            plan.push((src.clone(), dst));
+        }
+    }
+
+    // A target may only exist already if it is itself being renamed away.
+    let moving: HashSet<&PathBuf> = plan.iter().map(|(src, _)| src).collect();
+    for (_, dst) in &plan {
+        if dst.symlink_metadata().is_ok() && !moving.contains(dst) {
+            return Err(format!("{} already exists", dst.display()));
+        }
+    }
+
+    Ok(plan)
+}
+
+// `rename` replaces an existing target, so the target is checked right before each call.
+async fn rename_no_replace(src: &Path, dst: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(dst).await.is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "target already exists",
+        ));
+    }
+    fs::rename(src, dst).await
+}
+
+// Renames in two phases through temporary names so swaps and cycles never overwrite each
+// other. Returns one message per failed entry.
+pub async fn apply_renames(plan: &[(PathBuf, PathBuf)]) -> Vec<String> {
+    let mut errors = Vec::new();
+    let mut staged = Vec::new();
+
+    for (index, (src, dst)) in plan.iter().enumerate() {
+        let temp = src.with_file_name(format!(".arbor-rename-{}-{index}", std::process::id()));
+        match rename_no_replace(src, &temp).await {
+            Ok(()) => staged.push((src, temp, dst)),
+            Err(e) => {
+                errors.push(format!("{}: {e}", src.display()));
+                // Nothing has its final name yet, so undo everything.
+                for (src, temp, _) in staged.iter().rev() {
+                    let _ = fs::rename(temp, src).await;
+                }
+                return errors;
+            }
+        }
+    }
+
+    // A source name may already belong to another entry by now, so a file that can't take
+    // its new name stays under the temporary one instead of being moved back.
+    for (src, temp, dst) in staged {
+        if let Err(e) = rename_no_replace(&temp, dst).await {
+            errors.push(format!(
+                "{} -> {}: {e}; left as {}",
+                src.display(),
+                dst.display(),
+                temp.display()
+            ));
+        }
+    }
+
+    errors
+}
+
+impl CaseConversion {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Keep => Self::Lower,
+            Self::Lower => Self::Upper,
+            Self::Upper => Self::Title,
+            Self::Title => Self::Keep,
+        }
+    }
+
+    pub fn previous(self) -> Self {
+        match self {
+            Self::Keep => Self::Title,
+            Self::Lower => Self::Keep,
+            Self::Upper => Self::Lower,
+            Self::Title => Self::Upper,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Keep => "keep",
+            Self::Lower => "lower",
+            Self::Upper => "UPPER",
+            Self::Title => "Title",
+        }
+    }
+
+    fn apply(self, name: &str) -> String {
+        match self {
+            Self::Keep => name.to_string(),
+            Self::Lower => name.to_lowercase(),
+            Self::Upper => name.to_uppercase(),
+            Self::Title => {
+                let mut result = String::with_capacity(name.len());
+                let mut word_start = true;
+                for c in name.chars() {
+                    if word_start {
+                        result.extend(c.to_uppercase());
+                    } else {
+                        result.extend(c.to_lowercase());
+                    }
+                    // Dots are not word breaks, so extensions stay lowercase.
+                    word_start = c.is_whitespace() || c == '_' || c == '-';
+                }
+                result
+            }
+        }
+    }
+}
+
+impl PatternField {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Find => Self::Replace,
+            Self::Replace => Self::Case,
+            Self::Case => Self::Extension,
+            Self::Extension => Self::Find,
+        }
+    }
+
+    pub fn previous(self) -> Self {
+        match self {
+            Self::Find => Self::Extension,
+            Self::Replace => Self::Find,
+            Self::Case => Self::Replace,
+            Self::Extension => Self::Case,
+        }
+    }
+}
+
+// Computes the new name of every source. Find is a regex applied to the whole name and
+// Replace may use `$1`/`${name}` captures plus `{n}` or `{n:WIDTH}` sequence numbers.
+fn pattern_preview(rename: &PatternRename) -> Result<Vec<RenamePreviewRow>, String> {
+    let find = if rename.find().is_empty() {
+        None
+    } else {
+        Some(Regex::new(rename.find()).map_err(|e| e.to_string())?)
+    };
+
+    let token = Regex::new(r"\{n(?::(\d+))?\}").map_err(|e| e.to_string())?;
+    let mut widths = Vec::new();
+    let template = token
+        .replace_all(rename.replace(), |caps: &Captures| {
+            widths.push(
+                caps.get(1)
+                    .and_then(|w| w.as_str().parse().ok())
+                    .unwrap_or(0),
+            );
+            SEQUENCE_MARK.to_string()
+        })
+        .into_owned();
+
+    let names: Vec<String> = rename
+        .sources()
+        .iter()
+        .enumerate()
+        .map(|(index, source)| {
+            let name = source.file_name().unwrap_or_default().to_string_lossy();
+            let replaced = match &find {
+                Some(find) => find.replace_all(&name, template.as_str()).into_owned(),
+                None => name.to_string(),
+            };
+            let numbered = expand_sequence(&replaced, index + 1, &widths);
+            let converted = rename.case().apply(&numbered);
+            change_extension(&converted, rename.extension())
+        })
+        .collect();
+
+    let mut counts: HashMap<PathBuf, usize> = HashMap::new();
+    for (source, name) in rename.sources().iter().zip(&names) {
+        *counts.entry(rename_target(source, name)).or_default() += 1;
+    }
+    let moving: HashSet<&PathBuf> = rename.sources().iter().collect();
+
+    Ok(rename
+        .sources()
+        .iter()
+        .zip(names)
+        .map(|(source, new_name)| {
+            let target = rename_target(source, &new_name);
+            let status = if target == *source {
+                RenameStatus::Unchanged
+            } else if source.file_name().and_then(|name| name.to_str()).is_none() {
+                // A pattern can't change a name it only sees in its lossy form.
+                RenameStatus::NotUtf8
+            } else if new_name.is_empty()
+                || new_name.contains('/')
+                || new_name == "."
+                || new_name == ".."
+            {
+                RenameStatus::Invalid
+            } else if counts.get(&target).copied().unwrap_or(0) > 1
+                || (target.symlink_metadata().is_ok() && !moving.contains(&target))
+            {
+                RenameStatus::Collision
+            } else {
+                RenameStatus::Renamed
+            };
+            RenamePreviewRow {
+                source: source.clone(),
+                new_name,
+                status,
+            }
+        })
+        .collect())
+}
+
+fn expand_sequence(name: &str, number: usize, widths: &[usize]) -> String {
+    let mut result = String::with_capacity(name.len());
+    let mut token = 0;
+    for c in name.chars() {
+        if c == SEQUENCE_MARK {
+            let width = widths
+                .get(token % widths.len().max(1))
+                .copied()
+                .unwrap_or(0);
+            result.push_str(&format!("{number:0width$}"));
+            token += 1;
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+// An empty extension keeps the current one.
+fn change_extension(name: &str, extension: &str) -> String {
+    let extension = extension.trim_start_matches('.');
+    if extension.is_empty() {
+        return name.to_string();
+    }
+    let stem = Path::new(name).file_stem().map_or_else(
+        || name.to_string(),
+        |stem| stem.to_string_lossy().to_string(),
+    );
+    format!("{stem}.{extension}")
+}
+
+// A fresh file only the user can read; `create_new` refuses names planted by someone else,
+// symlinks included.
+async fn write_private_temp(contents: &[u8]) -> io::Result<PathBuf> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_nanos());
+    let temp =
+        std::env::temp_dir().join(format!("arbor-rename-{}-{nanos}.txt", std::process::id()));
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&temp)
+        .await?;
+    file.write_all(contents).await?;
+    // Tokio finishes writes in the background; the editor must see the whole list.
+    file.flush().await?;
+    Ok(temp)
+}
+
+impl FileManager {
+    // The marked entries, or every visible entry when nothing is marked.
+    fn rename_targets(&self) -> Vec<PathBuf> {
+        let selected = self.get_selected_paths();
+        if selected.is_empty() {
+            self.entries()
+                .iter()
+                .map(|entry| entry.entry_path().clone())
+                .collect()
+        } else {
+            selected
+        }
+    }
+
+    pub async fn bulk_rename_with_editor(&mut self) {
+        let sources = self.rename_targets();
+        if sources.is_empty() {
+            return;
+        }
+
+        let mut contents = String::new();
+        for path in &sources {
+            if let Some(name) = path.file_name() {
+                contents.push_str(&name.to_string_lossy());
+                contents.push('\n');
+            }
+        }
+
+        let temp = match write_private_temp(contents.as_bytes()).await {
+            Ok(temp) => temp,
+            Err(e) => {
+                self.show_notification(format!("Failed to create the rename list: {e}"));
+                return;
+            }
+        };
+
+        let status = self.run_in_terminal(editor_command().arg(&temp)).await;
+        let edited = fs::read_to_string(&temp).await;
+        let _ = fs::remove_file(&temp).await;
+
+        match status {
+            Ok(status) if status.success() => {}
+            Ok(status) => {
+                self.show_notification(format!("Editor exited with {status}, nothing renamed"));
+                return;
+            }
+            Err(e) => {
+                self.show_notification(format!("Failed to start editor: {e}"));
+                return;
+            }
+        }
+
+        let edited = match edited {
+            Ok(edited) => edited,
+            Err(e) => {
+                self.show_notification(e.to_string());
+                return;
+            }
+        };
+        let mut new_names: Vec<String> = edited
+            .lines()
+            .map(|line| line.trim_end_matches('\r').to_string())
+            .collect();
+        while new_names.last().is_some_and(String::is_empty) {
+            new_names.pop();
+        }
+
+        self.review_renames(plan_renames(&sources, &new_names));
+    }
+
+    // Opens the confirmation summary for a plan, or reports why there is nothing to apply.
+    pub fn review_renames(&mut self, plan: Result<Vec<(PathBuf, PathBuf)>, String>) {
+        match plan {
+            Ok(plan) if plan.is_empty() => self.show_notification("Nothing to rename".to_string()),
+            Ok(plan) => {
+                self.set_pending_renames(plan);
+                self.set_popup(PopupType::BulkRename);
+            }
+            Err(e) => self.show_notification(e),
+        }
+    }
+
+    pub async fn apply_pending_renames(&mut self) {
+        let plan = self.take_pending_renames();
+        let errors = apply_renames(&plan).await;
+        for (src, dst) in &plan {
+            self.invalidate_dir_sizes(src);
+            self.invalidate_dir_sizes(dst);
+        }
+
+        self.set_popup(PopupType::None);
+        self.set_mode(InteractionMode::Normal);
+        self.reload_current_directory().await;
+
+        if errors.is_empty() {
+            self.show_notification(format!("Renamed {} entries", plan.len()));
+        } else {
+            self.show_notification(errors.join("\n"));
+        }
+    }
+
+    pub fn cancel_pending_renames(&mut self) {
+        self.take_pending_renames();
+        self.set_popup(PopupType::None);
+    }
+
+    pub fn start_pattern_rename(&mut self) {
+        let sources = self.get_selected_paths();
+        if sources.is_empty() {
+            return;
+        }
+        self.set_pattern_rename(Some(PatternRename::new(sources)));
+        self.refresh_pattern_preview();
+        self.set_popup(PopupType::PatternRename);
+    }
+
+    // Compiles the pattern and checks the targets on disk, so it runs per edit, not per frame.
+    pub fn refresh_pattern_preview(&mut self) {
+        if let Some(rename) = self.pattern_rename_mut() {
+            let preview = pattern_preview(rename);
+            rename.set_preview(preview);
+        }
+    }
+
+    pub fn pattern_rename_key(&mut self, c: char) {
+        if let Some(rename) = self.pattern_rename_mut() {
+            if let Some(input) = rename.focused_input_mut() {
+                input.push(c);
+            }
+        }
+        self.refresh_pattern_preview();
+    }
+
+    pub fn pattern_rename_backspace(&mut self) {
+        if let Some(rename) = self.pattern_rename_mut() {
+            if let Some(input) = rename.focused_input_mut() {
+                input.pop();
+            }
+        }
+        self.refresh_pattern_preview();
+    }
+
+    pub fn pattern_rename_focus(&mut self, forward: bool) {
+        if let Some(rename) = self.pattern_rename_mut() {
+            let focus = if forward {
+                rename.focus().next()
+            } else {
+                rename.focus().previous()
+            };
+            rename.set_focus(focus);
+        }
+    }
+
+    pub fn pattern_rename_cycle_case(&mut self, forward: bool) {
+        if let Some(rename) = self.pattern_rename_mut() {
+            if rename.focus() == PatternField::Case {
+                let case = if forward {
+                    rename.case().next()
+                } else {
+                    rename.case().previous()
+                };
+                rename.set_case(case);
+            }
+        }
+        self.refresh_pattern_preview();
+    }
+
+    pub async fn apply_pattern_rename(&mut self) {
+        let Some(rename) = self.pattern_rename() else {
+            return;
+        };
+        let rows = match pattern_preview(rename) {
+            Ok(rows) => rows,
+            Err(e) => {
+                self.show_notification(e);
+                return;
+            }
+        };
+        if rows.iter().any(|row| {
+            matches!(
+                row.status,
+                RenameStatus::Invalid | RenameStatus::Collision | RenameStatus::NotUtf8
+            )
+        }) {
+            self.show_notification("Resolve the flagged names first".to_string());
+            return;
+        }
+
+        let sources = rename.sources().to_vec();
+        let names: Vec<String> = rows.into_iter().map(|row| row.new_name).collect();
+        match plan_renames(&sources, &names) {
+            Ok(plan) => {
+                let errors = apply_renames(&plan).await;
+                for (src, dst) in &plan {
+                    self.invalidate_dir_sizes(src);
+                    self.invalidate_dir_sizes(dst);
+                }
+                self.cancel_pattern_rename();
+                self.set_mode(InteractionMode::Normal);
+                self.reload_current_directory().await;
+                if errors.is_empty() {
+                    self.show_notification(format!("Renamed {} entries", plan.len()));
+                } else {
+                    self.show_notification(errors.join("\n"));
+                }
+            }
+            Err(e) => self.show_notification(e),
+        }
+    }
+
+    pub fn cancel_pattern_rename(&mut self) {
+        self.set_pattern_rename(None);
+        self.set_popup(PopupType::None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A scratch directory holding one file per name, each containing its own name.
+    fn scratch(test: &str, names: &[&str]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("arbor-test-{}-{test}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in names {
+            std::fs::write(dir.join(name), name).unwrap();
+        }
+        dir
+    }
+
+    fn contents(dir: &Path) -> Vec<(String, String)> {
+        let mut files: Vec<(String, String)> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let name = entry.file_name().to_string_lossy().to_string();
+                (name, std::fs::read_to_string(entry.path()).unwrap())
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    fn paths(dir: &Path, names: &[&str]) -> Vec<PathBuf> {
+        names.iter().map(|name| dir.join(name)).collect()
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn pair(a: &str, b: &str) -> (String, String) {
+        (a.to_string(), b.to_string())
+    }
+
+    #[test]
+    fn plan_rejects_duplicates_and_existing_targets() {
+        let dir = scratch("plan", &["a", "b", "c"]);
+        let sources = paths(&dir, &["a", "b"]);
+
+        assert!(plan_renames(&sources, &names(&["x", "x"])).is_err());
+        assert!(plan_renames(&sources, &names(&["c", "b"])).is_err());
+        assert!(plan_renames(&sources, &names(&["a/b", "b"])).is_err());
+        assert!(plan_renames(&sources, &names(&["x"])).is_err());
+        // Unchanged entries are left out; swapping onto a moving name is allowed.
+        assert_eq!(plan_renames(&sources, &names(&["a", "b"])), Ok(Vec::new()));
+        assert_eq!(
+            plan_renames(&sources, &names(&["b", "a"])).unwrap().len(),
+            2
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn plan_keeps_untouched_non_utf8_names() {
+        use std::os::unix::ffi::OsStrExt;
+        let dir = scratch("utf8", &[]);
+        let sources = vec![
+            dir.join(std::ffi::OsStr::from_bytes(b"caf\xe9")),
+            dir.join("b"),
+        ];
+
+        // The lossy line left as it was keeps the original bytes.
+        assert_eq!(
+            plan_renames(&sources, &names(&["caf\u{FFFD}", "c"])),
+            Ok(vec![(dir.join("b"), dir.join("c"))])
+        );
+        assert_eq!(
+            plan_renames(&sources, &names(&["cafe", "b"])),
+            Ok(vec![(sources[0].clone(), dir.join("cafe"))])
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn apply_swaps_two_entries() {
+        let dir = scratch("swap", &["a", "b"]);
+        let plan = plan_renames(&paths(&dir, &["a", "b"]), &names(&["b", "a"])).unwrap();
+
+        assert!(apply_renames(&plan).await.is_empty());
+        assert_eq!(contents(&dir), vec![pair("a", "b"), pair("b", "a")]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn apply_rotates_a_three_cycle() {
+        let dir = scratch("cycle", &["a", "b", "c"]);
+        let plan = plan_renames(&paths(&dir, &["a", "b", "c"]), &names(&["b", "c", "a"])).unwrap();
+
+        assert!(apply_renames(&plan).await.is_empty());
+        assert_eq!(
+            contents(&dir),
+            vec![pair("a", "c"), pair("b", "a"), pair("c", "b")]
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn apply_restores_everything_when_staging_fails() {
+        let dir = scratch("staging", &["a", "b"]);
+        let plan = plan_renames(&paths(&dir, &["a", "b"]), &names(&["b", "a"])).unwrap();
+        std::fs::remove_file(dir.join("b")).unwrap();
+
+        assert_eq!(apply_renames(&plan).await.len(), 1);
+        assert_eq!(contents(&dir), vec![pair("a", "a")]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn apply_never_overwrites_a_target_created_after_planning() {
+        let dir = scratch("late", &["a", "c"]);
+        let plan = plan_renames(&paths(&dir, &["a", "c"]), &names(&["b", "d"])).unwrap();
+        std::fs::write(dir.join("d"), "late").unwrap();
+
+        let errors = apply_renames(&plan).await;
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("left as"));
+        let files = contents(&dir);
+        assert!(files.contains(&pair("b", "a")));
+        assert!(files.contains(&pair("d", "late")));
+        // `c` is kept under its temporary name rather than lost.
+        assert!(files
+            .iter()
+            .any(|(name, text)| name.starts_with(".arbor-rename-") && text == "c"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
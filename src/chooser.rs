@@ -0,0 +1,84 @@
+use crate::modals::{Chooser, FileManager};
+use std::io::{self, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+
+impl Chooser {
+    // Every path is terminated by the separator, so NUL output splits cleanly with `xargs -0`.
+    // Nothing is written when Arbor was quit without choosing.
+    pub fn write(&self) -> io::Result<()> {
+        if self.chosen().is_empty() {
+            return Ok(());
+        }
+        let mut bytes = Vec::new();
+        for path in self.chosen() {
+            bytes.extend_from_slice(path.as_os_str().as_bytes());
+            bytes.push(self.separator());
+        }
+        match self.output() {
+            Some(path) => std::fs::write(path, bytes),
+            None => {
+                let mut out = io::stdout().lock();
+                out.write_all(&bytes)?;
+                out.flush()
+            }
+        }
+    }
+}
+
+impl FileManager {
+    // Chooses the marked entries, or the hovered one. Returns true when Arbor should exit.
+    pub async fn choose_selected(&mut self) -> bool {
+        let Some(chooser) = self.chooser() else {
+            return false;
+        };
+        let (dirs_only, multiple) = (chooser.dirs_only(), chooser.multiple());
+
+        let hovered = self
+            .get_selected_index_entry_unmut()
+            .map(|entry| entry.entry_path().clone());
+        let marked = if multiple {
+            self.get_selected_paths()
+        } else {
+            Vec::new()
+        };
+
+        // When picking files, Enter on a directory still walks into it.
+        if marked.is_empty() && !dirs_only && hovered.as_ref().is_some_and(|p| p.is_dir()) {
+            self.navigate_to_child().await;
+            return false;
+        }
+
+        let candidates = if marked.is_empty() {
+            hovered.into_iter().collect()
+        } else {
+            marked
+        };
+        let chosen: Vec<PathBuf> = candidates
+            .into_iter()
+            .filter(|path| path.is_dir() == dirs_only)
+            .collect();
+        if chosen.is_empty() {
+            let kind = if dirs_only { "directory" } else { "file" };
+            self.show_notification(format!("Choose a {kind}"));
+            return false;
+        }
+
+        if let Some(chooser) = self.chooser_mut() {
+            chooser.set_chosen(chosen);
+        }
+        true
+    }
+
+    // Directory pickers can also take the directory being shown.
+    pub fn choose_current_directory(&mut self) -> bool {
+        let current = self.current_path().clone();
+        match self.chooser_mut() {
+            Some(chooser) if chooser.dirs_only() => {
+                chooser.set_chosen(vec![current]);
+                true
+            }
+            _ => false,
+        }
+    }
+}
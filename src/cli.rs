@@ -0,0 +1,249 @@
+use crate::modals::SortMode;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+pub const HELP: &str = "\
+Arbor - a three-pane terminal file manager
+
+Usage: arbor [OPTIONS] [PATH]
+       arbor shell-init <bash|zsh|fish>
+
+Arguments:
+  [PATH]  Directory to open, or a file to open its directory with the cursor on it
+
+Options:
+  -c, --config <FILE>    Read the configuration from FILE
+  -a, --show-hidden      Show dotfiles
+  -s, --sort <MODE>      Sort by name, size, modified, extension or type
+  -r, --read-only        Refuse file changes, the editor and shell commands
+      --cwd-file <FILE>  Write the last directory to FILE on exit (not when quitting with Q)
+  -h, --help             Print this help
+  -V, --version          Print the version
+
+Chooser:
+      --chooser-file <FILE>  Pick paths and write them to FILE on exit ('-' for stdout)
+  -0, --null                 Terminate chosen paths with NUL instead of newline
+      --choose-dirs          Pick directories instead of files
+      --single               Allow only one path to be picked
+
+Commands:
+  shell-init <SHELL>  Print an `arbor` function that changes to the last directory on exit
+";
+
+// `command arbor` skips the function itself; the exit status of Arbor is kept.
+const POSIX_WRAPPER: &str = r#"arbor() {
+    local tmp dir ret
+    tmp="$(mktemp -t arbor-cwd.XXXXXX)" || return
+    command arbor --cwd-file "$tmp" "$@"
+    ret=$?
+    dir="$(cat -- "$tmp")"
+    rm -f -- "$tmp"
+    if [ -n "$dir" ] && [ "$dir" != "$PWD" ]; then
+        cd -- "$dir" || return
+    fi
+    return $ret
+}
+"#;
+
+const FISH_WRAPPER: &str = r#"function arbor
+    set -l tmp (mktemp -t arbor-cwd.XXXXXX); or return
+    command arbor --cwd-file $tmp $argv
+    set -l ret $status
+    set -l dir (cat -- $tmp)
+    rm -f -- $tmp
+    if test -n "$dir"; and test "$dir" != "$PWD"
+        cd -- $dir
+    end
+    return $ret
+end
+"#;
+
+#[derive(Debug, Default)]
+pub struct CliArgs {
+    pub path: Option<PathBuf>,
+    pub config: Option<PathBuf>,
+    pub show_hidden: bool,
+    pub sort: Option<SortMode>,
+    pub read_only: bool,
+    pub cwd_file: Option<PathBuf>,
+    pub chooser_file: Option<PathBuf>,
+    pub null: bool,
+    pub choose_dirs: bool,
+    pub single: bool,
+}
+
+#[derive(Debug)]
+pub enum CliCommand {
+    Run(CliArgs),
+    Help,
+    Version,
+    ShellInit(&'static str),
+}
+
+impl SortMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "name" | "natural" => Some(Self::Natural),
+            "size" => Some(Self::Size),
+            "modified" | "mtime" => Some(Self::Modified),
+            "extension" | "ext" => Some(Self::Extension),
+            "type" => Some(Self::Type),
+            _ => None,
+        }
+    }
+}
+
+// Accepts `--flag value` and `--flag=value`; everything after `--` is positional.
+pub fn parse(args: impl IntoIterator<Item = OsString>) -> Result<CliCommand, String> {
+    let mut parsed = CliArgs::default();
+    let mut args = args.into_iter().peekable();
+    let mut positional_only = false;
+
+    // `./shell-init` still opens a directory of that name.
+    if args.peek().is_some_and(|arg| arg == "shell-init") {
+        args.next();
+        let shell = args.next().map(|arg| arg.to_string_lossy().to_string());
+        return match shell.as_deref() {
+            Some("bash" | "zsh") => Ok(CliCommand::ShellInit(POSIX_WRAPPER)),
+            Some("fish") => Ok(CliCommand::ShellInit(FISH_WRAPPER)),
+            Some(other) => Err(format!(
+                "unsupported shell '{other}', expected bash, zsh or fish"
+            )),
+            None => Err("'shell-init' needs a shell: bash, zsh or fish".to_string()),
+        };
+    }
+
+    while let Some(arg) = args.next() {
+        let text = arg.to_string_lossy().to_string();
+        if positional_only || !text.starts_with('-') || text == "-" {
+            if parsed.path.is_some() {
+                return Err(format!("unexpected argument '{text}'"));
+            }
+            parsed.path = Some(PathBuf::from(arg));
+            continue;
+        }
+
+        let (flag, inline) = match text.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => {
+                (flag.to_string(), Some(OsString::from(value)))
+            }
+            _ => (text.clone(), None),
+        };
+        let mut value = |name: &str| {
+            inline
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("'{name}' needs a value"))
+        };
+
+        match flag.as_str() {
+            "--" => positional_only = true,
+            "-h" | "--help" => return Ok(CliCommand::Help),
+            "-V" | "--version" => return Ok(CliCommand::Version),
+            "-c" | "--config" => parsed.config = Some(PathBuf::from(value(&flag)?)),
+            "-a" | "--show-hidden" => parsed.show_hidden = true,
+            "-r" | "--read-only" => parsed.read_only = true,
+            "-s" | "--sort" => {
+                let mode = value(&flag)?.to_string_lossy().to_string();
+                parsed.sort = Some(
+                    SortMode::parse(&mode).ok_or_else(|| format!("invalid sort mode '{mode}'"))?,
+                );
+            }
+            "--cwd-file" => parsed.cwd_file = Some(PathBuf::from(value(&flag)?)),
+            "--chooser-file" => parsed.chooser_file = Some(PathBuf::from(value(&flag)?)),
+            "-0" | "--null" => parsed.null = true,
+            "--choose-dirs" => parsed.choose_dirs = true,
+            "--single" => parsed.single = true,
+            _ => return Err(format!("unknown option '{flag}'")),
+        }
+    }
+
+    if parsed.chooser_file.is_none() && (parsed.null || parsed.choose_dirs || parsed.single) {
+        return Err("'--null', '--choose-dirs' and '--single' need '--chooser-file'".to_string());
+    }
+
+    Ok(CliCommand::Run(parsed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(args: &[&str]) -> Result<CliArgs, String> {
+        match parse(args.iter().map(OsString::from))? {
+            CliCommand::Run(args) => Ok(args),
+            other => panic!("expected Run, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn takes_values_inline_or_separately() {
+        let args = run(&[
+            "--sort=size",
+            "-c",
+            "arbor.toml",
+            "--cwd-file=/tmp/cwd",
+            "dir",
+        ])
+        .unwrap();
+        assert_eq!(args.sort, Some(SortMode::Size));
+        assert_eq!(args.config, Some(PathBuf::from("arbor.toml")));
+        assert_eq!(args.cwd_file, Some(PathBuf::from("/tmp/cwd")));
+        assert_eq!(args.path, Some(PathBuf::from("dir")));
+
+        // A value may itself contain `=`.
+        let args = run(&["--config=a=b.toml"]).unwrap();
+        assert_eq!(args.config, Some(PathBuf::from("a=b.toml")));
+    }
+
+    #[test]
+    fn rejects_missing_and_invalid_values() {
+        assert_eq!(run(&["--config"]).unwrap_err(), "'--config' needs a value");
+        assert_eq!(run(&["-s"]).unwrap_err(), "'-s' needs a value");
+        assert_eq!(
+            run(&["--sort", "colour"]).unwrap_err(),
+            "invalid sort mode 'colour'"
+        );
+        assert_eq!(run(&["--bogus"]).unwrap_err(), "unknown option '--bogus'");
+    }
+
+    #[test]
+    fn handles_positionals() {
+        let args = run(&["--", "-r"]).unwrap();
+        assert_eq!(args.path, Some(PathBuf::from("-r")));
+        assert!(!args.read_only);
+
+        assert_eq!(run(&["-"]).unwrap().path, Some(PathBuf::from("-")));
+        assert_eq!(run(&["a", "b"]).unwrap_err(), "unexpected argument 'b'");
+        // Only the first argument can be the subcommand.
+        assert_eq!(
+            run(&["-r", "shell-init"]).unwrap().path,
+            Some(PathBuf::from("shell-init"))
+        );
+    }
+
+    #[test]
+    fn prints_shell_wrappers() {
+        let init = |args: &[&str]| parse(args.iter().map(OsString::from));
+        assert!(matches!(
+            init(&["shell-init", "zsh"]),
+            Ok(CliCommand::ShellInit(POSIX_WRAPPER))
+        ));
+        assert!(matches!(
+            init(&["shell-init", "fish"]),
+            Ok(CliCommand::ShellInit(FISH_WRAPPER))
+        ));
+        assert!(init(&["shell-init"]).is_err());
+        assert!(init(&["shell-init", "tcsh"]).is_err());
+    }
+
+    #[test]
+    fn chooser_flags_need_a_chooser_file() {
+        for flag in ["-0", "--null", "--choose-dirs", "--single"] {
+            assert!(run(&[flag]).is_err(), "{flag}");
+        }
+        let args = run(&["--chooser-file", "-", "--single", "-0"]).unwrap();
+        assert_eq!(args.chooser_file, Some(PathBuf::from("-")));
+        assert!(args.single && args.null);
+    }
+}
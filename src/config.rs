@@ -0,0 +1,110 @@
+use anyhow::{Context, Result};
+use globset::Glob;
+use serde::Deserialize;
+use std::env;
+use std::path::{Path, PathBuf};
+
+// Maps files to a command. A rule matches when its `mime` or `glob` matches, or always when
+// it has neither. The command runs through `sh -c` with the files as "$@".
+#[derive(Debug, Clone, Deserialize)]
+pub struct OpenerRule {
+    name: String,
+    #[serde(default)]
+    mime: Option<String>,
+    #[serde(default)]
+    glob: Option<String>,
+    command: String,
+    // Terminal programs take over the screen; the rest are started detached.
+    #[serde(default)]
+    terminal: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    #[serde(default = "default_openers")]
+    opener: Vec<OpenerRule>,
+}
+
+fn default_openers() -> Vec<OpenerRule> {
+    vec![
+        OpenerRule {
+            name: "Edit".to_string(),
+            mime: Some("text/*".to_string()),
+            glob: None,
+            command: r#"${VISUAL:-${EDITOR:-vi}} "$@""#.to_string(),
+            terminal: true,
+        },
+        OpenerRule {
+            name: "Open".to_string(),
+            mime: None,
+            glob: None,
+            command: r#"for f in "$@"; do xdg-open "$f"; done"#.to_string(),
+            terminal: false,
+        },
+    ]
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            opener: default_openers(),
+        }
+    }
+}
+
+impl Config {
+    // `$XDG_CONFIG_HOME/arbor/config.toml`, falling back to `~/.config/arbor/config.toml`.
+    pub fn default_path() -> Option<PathBuf> {
+        env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .map(|dir| dir.join("arbor").join("config.toml"))
+    }
+
+    // A missing file is not an error; a malformed one is.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let config: Self =
+            toml::from_str(&text).with_context(|| format!("Invalid config {}", path.display()))?;
+        // A glob that doesn't compile would otherwise just never match.
+        for rule in &config.opener {
+            if let Some(pattern) = rule.glob() {
+                Glob::new(pattern).with_context(|| {
+                    format!(
+                        "Invalid config {}: bad glob in opener \"{}\"",
+                        path.display(),
+                        rule.name()
+                    )
+                })?;
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn openers(&self) -> &[OpenerRule] {
+        &self.opener
+    }
+}
+
+impl OpenerRule {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn mime(&self) -> Option<&str> {
+        self.mime.as_deref()
+    }
+    pub fn glob(&self) -> Option<&str> {
+        self.glob.as_deref()
+    }
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+    pub fn terminal(&self) -> bool {
+        self.terminal
+    }
+}
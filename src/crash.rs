@@ -0,0 +1,139 @@
+use chrono::Local;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const MAX_ACTIONS: usize = 20;
+
+enum Action {
+    Key(String),
+    // Text typed into a prompt is only counted; it may be a command holding a password.
+    Typed { context: String, chars: usize },
+}
+
+// What the crash report knows about the session; kept up to date by the event loop.
+struct Context {
+    path: Option<PathBuf>,
+    actions: VecDeque<Action>,
+}
+
+static CONTEXT: Mutex<Context> = Mutex::new(Context {
+    path: None,
+    actions: VecDeque::new(),
+});
+
+fn describe_key(key: &KeyEvent) -> String {
+    let mut name = String::new();
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        name.push_str("Ctrl-");
+    }
+    if key.modifiers.contains(KeyModifiers::ALT) {
+        name.push_str("Alt-");
+    }
+    match key.code {
+        KeyCode::Char(' ') => name.push_str("Space"),
+        KeyCode::Char(c) => name.push(c),
+        code => name.push_str(&format!("{code:?}")),
+    }
+    name
+}
+
+fn push_action(state: &mut Context, action: Action) {
+    if state.actions.len() == MAX_ACTIONS {
+        state.actions.pop_front();
+    }
+    state.actions.push_back(action);
+}
+
+pub fn record_key(key: &KeyEvent, context: &str) {
+    if let Ok(mut state) = CONTEXT.lock() {
+        push_action(
+            &mut state,
+            Action::Key(format!("{} ({context})", describe_key(key))),
+        );
+    }
+}
+
+// Consecutive keystrokes in the same prompt add up to one action.
+pub fn record_typing(context: &str) {
+    if let Ok(mut state) = CONTEXT.lock() {
+        if let Some(Action::Typed {
+            context: last,
+            chars,
+        }) = state.actions.back_mut()
+        {
+            if last == context {
+                *chars += 1;
+                return;
+            }
+        }
+        push_action(
+            &mut state,
+            Action::Typed {
+                context: context.to_string(),
+                chars: 1,
+            },
+        );
+    }
+}
+
+pub fn record_path(path: &Path) {
+    if let Ok(mut state) = CONTEXT.lock() {
+        state.path = Some(path.to_path_buf());
+    }
+}
+
+// `$XDG_STATE_HOME/arbor`, falling back to `~/.local/state/arbor`.
+fn state_dir() -> Option<PathBuf> {
+    env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))
+        .map(|dir| dir.join("arbor"))
+}
+
+// Called from the panic hook, so the lock is only tried: the panic may have happened under it.
+pub fn write_report(info: &PanicHookInfo) -> io::Result<PathBuf> {
+    let dir = state_dir().ok_or_else(|| io::Error::other("no state directory"))?;
+    let now = Local::now();
+    let mut report = format!(
+        "arbor {} crashed at {}\n\n{info}\n",
+        env!("CARGO_PKG_VERSION"),
+        now.format("%Y-%m-%d %H:%M:%S %z")
+    );
+
+    match CONTEXT.try_lock() {
+        Ok(state) => {
+            let path = state
+                .path
+                .as_ref()
+                .map_or("unknown".to_string(), |p| p.display().to_string());
+            let _ = writeln!(
+                report,
+                "\nCurrent path: {path}\n\nLast actions, oldest first:"
+            );
+            for action in &state.actions {
+                let _ = match action {
+                    Action::Key(key) => writeln!(report, "  {key}"),
+                    Action::Typed { context, chars } => {
+                        writeln!(report, "  typed {chars} chars ({context})")
+                    }
+                };
+            }
+        }
+        Err(_) => report.push_str("\nSession state unavailable\n"),
+    }
+    let _ = write!(report, "\nBacktrace:\n{}", Backtrace::force_capture());
+
+    fs::create_dir_all(&dir)?;
+    let file = dir.join(format!("crash-{}.log", now.format("%Y%m%d-%H%M%S")));
+    fs::write(&file, report)?;
+    Ok(file)
+}
@@ -0,0 +1,116 @@
+use crate::modals::{FileManager, FsEntryType};
+use crate::utils::format_size;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Apparent size of everything below `dir`. Symlinks are not followed and unreadable entries
+// are skipped.
+pub fn dir_size(dir: &Path) -> u64 {
+    let mut total = 0;
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                stack.push(entry.path());
+            } else {
+                total += metadata.len();
+            }
+        }
+    }
+    total
+}
+
+impl FileManager {
+    // Rescans even when a size is cached, so `z` doubles as a refresh. Each scan is tagged so
+    // only the latest one for a path is accepted.
+    fn scan_dir_size(&mut self, path: PathBuf) {
+        if self.dir_sizes_pending().contains_key(&path) {
+            return;
+        }
+        let generation = self.dir_size_generation() + 1;
+        self.set_dir_size_generation(generation);
+        self.dir_sizes_pending_mut()
+            .insert(path.clone(), generation);
+        self.dir_sizes_mut().remove(&path);
+        let tx = self.dir_size_tx().clone();
+        tokio::task::spawn_blocking(move || {
+            let size = dir_size(&path);
+            let _ = tx.send((generation, path, size));
+        });
+    }
+
+    fn scan_dir_sizes(&mut self, paths: Vec<PathBuf>) {
+        if paths.is_empty() {
+            self.show_notification("No directories to measure".to_string());
+        }
+        for path in paths {
+            self.scan_dir_size(path);
+        }
+    }
+
+    pub fn size_hovered_dir(&mut self) {
+        let paths = self
+            .get_selected_index_entry_unmut()
+            .filter(|entry| *entry.entry_type() == FsEntryType::Directory)
+            .map(|entry| vec![entry.entry_path().clone()])
+            .unwrap_or_default();
+        self.scan_dir_sizes(paths);
+    }
+
+    pub fn size_listed_dirs(&mut self) {
+        let paths = self
+            .entries()
+            .iter()
+            .filter(|entry| *entry.entry_type() == FsEntryType::Directory)
+            .map(|entry| entry.entry_path().clone())
+            .collect();
+        self.scan_dir_sizes(paths);
+    }
+
+    pub fn size_selected_dirs(&mut self) {
+        let paths = self
+            .get_selected_paths()
+            .into_iter()
+            .filter(|path| path.is_dir() && !path.is_symlink())
+            .collect();
+        self.scan_dir_sizes(paths);
+    }
+
+    pub fn handle_dir_size(&mut self, (generation, path, size): (u64, PathBuf, u64)) {
+        // Dropped when the directory changed while it was being measured, or was rescanned.
+        if self.dir_sizes_pending().get(&path) == Some(&generation) {
+            self.dir_sizes_pending_mut().remove(&path);
+            self.dir_sizes_mut().insert(path, size);
+        }
+    }
+
+    // Forgets sizes that include `changed`: its ancestors and everything below it.
+    pub fn invalidate_dir_sizes(&mut self, changed: &Path) {
+        let affected = |path: &PathBuf| path.starts_with(changed) || changed.starts_with(path);
+        self.dir_sizes_mut().retain(|path, _| !affected(path));
+        self.dir_sizes_pending_mut()
+            .retain(|path, _| !affected(path));
+    }
+
+    // After external programs, which may have changed anything.
+    pub fn forget_dir_sizes(&mut self) {
+        self.dir_sizes_mut().clear();
+        self.dir_sizes_pending_mut().clear();
+    }
+
+    pub fn dir_size_label(&self, path: &Path) -> Option<String> {
+        if let Some(size) = self.dir_sizes().get(path) {
+            Some(format_size(*size))
+        } else if self.dir_sizes_pending().contains_key(path) {
+            Some("…".to_string())
+        } else {
+            None
+        }
+    }
+}
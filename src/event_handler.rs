@@ -1,33 +1,173 @@
+use crate::crash;
+use crate::modals::{
+    Action, AnalyzerAction, DuNode, GitInfo, GrepEvent, PermissionField, ShellEvent,
+};
+use crate::terminal::Tui;
 use crate::{FileManager, InteractionMode, PopupType};
 use anyhow::Result;
-use crossterm::event::{self, Event, KeyCode, KeyEvent};
-use ratatui::DefaultTerminal;
-use tokio::time::{interval, Duration};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use futures::StreamExt;
+use std::future::{pending, Future};
+use std::io;
+use std::path::PathBuf;
+use tokio::sync::oneshot::error::RecvError;
+use tokio::time::{sleep, sleep_until, Duration, Instant};
+
+// The analyzer reports progress through a counter rather than messages.
+const SCAN_REFRESH: Duration = Duration::from_millis(100);
+
+// What woke the event loop up.
+enum Wake {
+    Input(Option<io::Result<Event>>),
+    Shell(ShellEvent),
+    Git((u64, Option<GitInfo>)),
+    DirSize((u64, PathBuf, u64)),
+    Grep(Option<GrepEvent>),
+    Analyzer(Result<DuNode, RecvError>),
+    Timer,
+}
+
+// Waits on `future`, or forever when there is nothing to wait on.
+async fn or_pending<F: Future>(future: Option<F>) -> F::Output {
+    match future {
+        Some(future) => future.await,
+        None => pending().await,
+    }
+}
 
 impl FileManager {
-    pub async fn run(mut self, mut terminal: DefaultTerminal) -> Result<()> {
-        let mut tick = interval(Duration::from_millis(16));
+    // Sleeps until something happens and only draws when it changed what is on screen.
+    pub async fn run(&mut self, terminal: &mut Tui) -> Result<()> {
+        let mut dirty = true;
 
         loop {
-            tick.tick().await;
+            if dirty {
+                if self.take_full_redraw() {
+                    terminal.clear()?;
+                }
+                terminal.draw(|f| self.render(f))?;
+            }
 
-            terminal.draw(|f| self.render(f))?;
+            let deadline = self.notification_deadline().map(Instant::from_std);
+            let scanning = self.is_scanning();
+            let wake = {
+                let sources = self.event_sources();
+                tokio::select! {
+                    event = sources.input.next() => Wake::Input(event),
+                    Some(event) = sources.shell.recv() => Wake::Shell(event),
+                    Some(status) = sources.git.recv() => Wake::Git(status),
+                    Some(size) = sources.dir_sizes.recv() => Wake::DirSize(size),
+                    event = or_pending(sources.grep.map(|grep| grep.recv())) => Wake::Grep(event),
+                    tree = or_pending(sources.analyzer) => Wake::Analyzer(tree),
+                    _ = sleep(SCAN_REFRESH), if scanning => Wake::Timer,
+                    _ = or_pending(deadline.map(sleep_until)) => Wake::Timer,
+                }
+            };
 
-            while event::poll(Duration::from_millis(0))? {
-                let evt = event::read()?;
-                if let Event::Key(key) = evt {
-                    if self.process_key(key).await? {
-                        return Ok(());
+            dirty = match wake {
+                // The terminal went away.
+                Wake::Input(None) => return Ok(()),
+                Wake::Input(Some(event)) => match event? {
+                    Event::Key(key) => {
+                        let popup = self.popup().clone();
+                        let context = format!("{:?}, {popup:?}", self.mode());
+                        if takes_text(&popup) && is_typing(&key) {
+                            crash::record_typing(&context);
+                        } else {
+                            crash::record_key(&key, &context);
+                        }
+                        if self.process_key(key).await? {
+                            self.close_input();
+                            return Ok(());
+                        }
+                        crash::record_path(self.current_path());
+                        true
                     }
+                    Event::Paste(text) => self.paste(&text).await,
+                    Event::Resize(..) => true,
+                    _ => false,
+                },
+                Wake::Shell(event) => {
+                    self.handle_shell_event(event).await;
+                    true
                 }
-            }
+                Wake::Git(status) => {
+                    self.handle_git_status(status);
+                    true
+                }
+                Wake::DirSize(size) => {
+                    self.handle_dir_size(size);
+                    true
+                }
+                Wake::Grep(event) => {
+                    self.handle_grep_event(event);
+                    true
+                }
+                Wake::Analyzer(tree) => {
+                    self.finish_analyzer(tree);
+                    true
+                }
+                Wake::Timer => {
+                    self.clear_expired_notifications();
+                    true
+                }
+            };
+        }
+    }
 
-            self.clear_expired_notifications();
+    // Pasted text goes straight into the focused text field; it is never replayed as keys,
+    // so it can't toggle options or run commands. Returns whether anything changed.
+    async fn paste(&mut self, text: &str) -> bool {
+        let text: String = text.chars().filter(|c| !c.is_control()).collect();
+        match self.popup() {
+            PopupType::Rename | PopupType::Create | PopupType::Grep | PopupType::Shell => {
+                self.mut_input_buffer().push_str(&text)
+            }
+            PopupType::Search => {
+                self.mut_input_buffer().push_str(&text);
+                self.update_search().await;
+            }
+            PopupType::Filter => {
+                self.mut_input_buffer().push_str(&text);
+                self.update_filter().await;
+            }
+            PopupType::PatternRename => {
+                if let Some(input) = self
+                    .pattern_rename_mut()
+                    .and_then(|rename| rename.focused_input_mut())
+                {
+                    input.push_str(&text);
+                }
+                self.refresh_pattern_preview();
+            }
+            PopupType::Permissions => {
+                if let Some(editor) = self.permission_editor_mut() {
+                    if editor.focus() == PermissionField::Expression {
+                        editor.expression_mut().push_str(&text);
+                    }
+                }
+            }
+            PopupType::Ownership => {
+                if let Some(input) = self
+                    .ownership_editor_mut()
+                    .and_then(|editor| editor.focused_input_mut())
+                {
+                    input.push_str(&text);
+                }
+            }
+            PopupType::Symlink => {
+                if let Some(symlink) = self.new_symlink_mut() {
+                    symlink.focused_input_mut().push_str(&text);
+                }
+            }
+            _ => return false,
         }
+        true
     }
 
     async fn process_key(&mut self, key: KeyEvent) -> Result<bool> {
         let mut buffer = self.input_buffer().clone();
+        let analyzer_confirming = self.analyzer().is_some_and(|a| a.confirm().is_some());
         match self.popup() {
             PopupType::Confirm => {
                 match key.code {
@@ -68,14 +208,220 @@ impl FileManager {
                 }
                 return Ok(false);
             }
+            PopupType::Search => {
+                match key.code {
+                    KeyCode::Char(c) => {
+                        self.mut_input_buffer().push(c);
+                        self.update_search().await;
+                    }
+                    KeyCode::Backspace => {
+                        self.mut_input_buffer().pop();
+                        self.update_search().await;
+                    }
+                    KeyCode::Enter => self.confirm_search(),
+                    KeyCode::Esc => self.cancel_search().await,
+                    _ => {}
+                }
+                return Ok(false);
+            }
+            PopupType::Filter => {
+                match key.code {
+                    KeyCode::Char(c) => {
+                        self.mut_input_buffer().push(c);
+                        self.update_filter().await;
+                    }
+                    KeyCode::Backspace => {
+                        self.mut_input_buffer().pop();
+                        self.update_filter().await;
+                    }
+                    KeyCode::Tab => self.cycle_filter_kind().await,
+                    KeyCode::Enter => self.confirm_filter(),
+                    KeyCode::Esc => self.clear_filter().await,
+                    _ => {}
+                }
+                return Ok(false);
+            }
+            PopupType::Grep => {
+                match key.code {
+                    KeyCode::Char(c) => self.mut_input_buffer().push(c),
+                    KeyCode::Backspace => {
+                        self.mut_input_buffer().pop();
+                    }
+                    KeyCode::Enter => self.run_content_search(),
+                    KeyCode::Esc => self.set_popup(PopupType::None),
+                    _ => {}
+                }
+                return Ok(false);
+            }
+            PopupType::GrepResults => {
+                match key.code {
+                    KeyCode::Char('j') | KeyCode::Down => self.grep_navigate(true),
+                    KeyCode::Char('k') | KeyCode::Up => self.grep_navigate(false),
+                    KeyCode::Enter => self.open_grep_result().await,
+                    KeyCode::Esc => self.close_content_search(),
+                    _ => {}
+                }
+                return Ok(false);
+            }
+            PopupType::BulkRename => {
+                match key.code {
+                    KeyCode::Char('y') | KeyCode::Enter => self.apply_pending_renames().await,
+                    KeyCode::Char('n') | KeyCode::Esc => self.cancel_pending_renames(),
+                    _ => {}
+                }
+                return Ok(false);
+            }
+            PopupType::PatternRename => {
+                match key.code {
+                    KeyCode::Tab => self.pattern_rename_focus(true),
+                    KeyCode::BackTab => self.pattern_rename_focus(false),
+                    KeyCode::Right => self.pattern_rename_cycle_case(true),
+                    KeyCode::Left => self.pattern_rename_cycle_case(false),
+                    KeyCode::Char(c) => self.pattern_rename_key(c),
+                    KeyCode::Backspace => self.pattern_rename_backspace(),
+                    KeyCode::Enter => self.apply_pattern_rename().await,
+                    KeyCode::Esc => self.cancel_pattern_rename(),
+                    _ => {}
+                }
+                return Ok(false);
+            }
+            PopupType::OpenWith => {
+                match key.code {
+                    KeyCode::Char('j') | KeyCode::Down => self.open_with_navigate(true),
+                    KeyCode::Char('k') | KeyCode::Up => self.open_with_navigate(false),
+                    KeyCode::Enter => self.open_with_selected().await,
+                    KeyCode::Esc => self.close_open_with(),
+                    _ => {}
+                }
+                return Ok(false);
+            }
+            PopupType::Shell => {
+                match key.code {
+                    KeyCode::Char(c) => self.mut_input_buffer().push(c),
+                    KeyCode::Backspace => {
+                        self.mut_input_buffer().pop();
+                    }
+                    KeyCode::Tab => self.toggle_shell_mode(),
+                    KeyCode::Enter => self.run_shell_command().await,
+                    KeyCode::Esc => self.set_popup(PopupType::None),
+                    _ => {}
+                }
+                return Ok(false);
+            }
+            PopupType::ShellLog => {
+                match key.code {
+                    KeyCode::Char('j') | KeyCode::Down => self.shell_log_navigate(true),
+                    KeyCode::Char('k') | KeyCode::Up => self.shell_log_navigate(false),
+                    KeyCode::Esc | KeyCode::Char('q') => self.set_popup(PopupType::None),
+                    _ => {}
+                }
+                return Ok(false);
+            }
+            PopupType::GitRestore => {
+                match key.code {
+                    KeyCode::Char('y') | KeyCode::Enter => self.apply_git_restore().await,
+                    KeyCode::Char('n') | KeyCode::Esc => self.cancel_git_restore(),
+                    _ => {}
+                }
+                return Ok(false);
+            }
+            PopupType::Analyzer if analyzer_confirming => {
+                match key.code {
+                    KeyCode::Char('y') | KeyCode::Enter => self.analyzer_apply().await,
+                    KeyCode::Char('n') | KeyCode::Esc => self.analyzer_cancel_confirm(),
+                    _ => {}
+                }
+                return Ok(false);
+            }
+            PopupType::Analyzer => {
+                match key.code {
+                    KeyCode::Char('j') | KeyCode::Down => self.analyzer_navigate(true),
+                    KeyCode::Char('k') | KeyCode::Up => self.analyzer_navigate(false),
+                    KeyCode::Char('l') | KeyCode::Right | KeyCode::Enter => self.analyzer_enter(),
+                    KeyCode::Char('h') | KeyCode::Left => self.analyzer_leave(),
+                    KeyCode::Char('s') => self.analyzer_toggle_size(),
+                    KeyCode::Char('d') => self.analyzer_confirm(AnalyzerAction::Delete),
+                    KeyCode::Char('t') => self.analyzer_confirm(AnalyzerAction::Trash),
+                    KeyCode::Char('q') | KeyCode::Esc => self.close_analyzer().await,
+                    _ => {}
+                }
+                return Ok(false);
+            }
+            PopupType::Info => {
+                if let KeyCode::Esc | KeyCode::Char('q' | 'i') = key.code {
+                    self.close_info();
+                }
+                return Ok(false);
+            }
+            PopupType::Permissions => {
+                match key.code {
+                    KeyCode::Tab => self.permission_focus(true),
+                    KeyCode::BackTab => self.permission_focus(false),
+                    KeyCode::Up => self.permission_move(-1, 0),
+                    KeyCode::Down => self.permission_move(1, 0),
+                    KeyCode::Left => self.permission_move(0, -1),
+                    KeyCode::Right => self.permission_move(0, 1),
+                    KeyCode::Char(c) => self.permission_key(c),
+                    KeyCode::Backspace => self.permission_backspace(),
+                    KeyCode::Enter => self.apply_permission_editor().await,
+                    KeyCode::Esc => self.cancel_permission_editor(),
+                    _ => {}
+                }
+                return Ok(false);
+            }
+            PopupType::Ownership => {
+                match key.code {
+                    KeyCode::Up => self.ownership_focus(false),
+                    KeyCode::Down => self.ownership_focus(true),
+                    KeyCode::Tab => self.ownership_complete(),
+                    KeyCode::Left => self.ownership_cycle_recursive(false),
+                    KeyCode::Right => self.ownership_cycle_recursive(true),
+                    KeyCode::Char(c) => self.ownership_key(c),
+                    KeyCode::Backspace => self.ownership_backspace(),
+                    KeyCode::Enter => self.apply_ownership_editor().await,
+                    KeyCode::Esc => self.cancel_ownership_editor(),
+                    _ => {}
+                }
+                return Ok(false);
+            }
+            PopupType::PasteLink => {
+                match key.code {
+                    KeyCode::Char('s') => self.paste_links(Action::Symlink).await,
+                    KeyCode::Char('r') => self.paste_links(Action::RelativeSymlink).await,
+                    KeyCode::Char('h') => self.paste_links(Action::Hardlink).await,
+                    KeyCode::Esc => self.set_popup(PopupType::None),
+                    _ => {}
+                }
+                return Ok(false);
+            }
+            PopupType::Symlink => {
+                match key.code {
+                    KeyCode::Tab | KeyCode::BackTab => self.symlink_focus(),
+                    KeyCode::Char(c) => self.symlink_key(c),
+                    KeyCode::Backspace => self.symlink_backspace(),
+                    KeyCode::Enter => self.apply_symlink().await,
+                    KeyCode::Esc => self.cancel_symlink(),
+                    _ => {}
+                }
+                return Ok(false);
+            }
             PopupType::None => {}
         }
 
+        if modifies_files(self.mode(), key.code) && !self.ensure_writable() {
+            return Ok(false);
+        }
+
         match self.mode() {
             InteractionMode::Normal => {
                 use KeyCode::*;
                 match key.code {
                     Char('q') => return Ok(true),
+                    // Quit without handing the directory to the shell wrapper.
+                    Char('Q') => {
+                        self.set_cwd_file(None);
+                        return Ok(true);
+                    }
                     Char('j') => self.navigate_down().await,
                     Char('k') => self.navigate_up().await,
                     Char('h') => self.navigate_to_parent().await,
@@ -86,8 +432,44 @@ impl FileManager {
                     Char('y') => self.copy_selected_entries().await,
                     Char('x') => self.move_selected_entries().await,
                     Char('p') => self.paste_clipboard().await,
+                    Char('>') => self.open_paste_link(),
+                    Char('@') => self.start_symlink(),
+                    Char('/') => self.start_search(),
+                    Char('n') => self.search_next(true).await,
+                    Char('N') => self.search_next(false).await,
+                    Char('f') => self.start_filter(),
+                    Char('S') => self.start_content_search(),
+                    Char('s') => self.cycle_sort_mode().await,
+                    Char('R') => self.toggle_sort_reverse().await,
+                    Char('D') => self.toggle_dirs_first().await,
+                    Char('b') => self.bulk_rename_with_editor().await,
+                    Char('.') => self.toggle_hidden().await,
+                    Char('I') => self.toggle_ignore_rules().await,
+                    Esc if self.filter().is_some() => self.clear_filter().await,
                     Esc => self.deselect_all().await,
+                    Enter if self.chooser().is_some() => return Ok(self.choose_selected().await),
+                    Char('c') if self.chooser().is_some_and(|c| c.dirs_only()) => {
+                        return Ok(self.choose_current_directory())
+                    }
                     Enter => self.operation().await?,
+                    Char('O') => self.open_with_menu(),
+                    Char('e') => self.edit_selected().await,
+                    Char(':') | Char('!') => self.start_shell_prompt(),
+                    Char('L') => self.open_shell_log(),
+                    Char('T') => self.spawn_subshell().await,
+                    Char('A') => self.git_stage().await,
+                    Char('U') => self.git_unstage().await,
+                    Char('X') => self.git_restore_prompt(),
+                    Char('G') => self.git_diff_preview().await,
+                    Char('z') => self.size_hovered_dir(),
+                    Char('Z') => self.size_listed_dirs(),
+                    Char('u') => self.start_analyzer(),
+                    Char('i') => self.open_info(),
+                    Char('P') => self.open_permission_editor(),
+                    Char('o') => self.open_ownership_editor(),
+                    Char('v') if self.chooser().is_some_and(|c| !c.multiple()) => {
+                        self.show_notification("Only one path can be chosen".to_string())
+                    }
                     Char('v') => {
                         self.set_mode(InteractionMode::MultiSelect);
                         if let Some(idx) = self.selection().selected() {
@@ -104,9 +486,24 @@ impl FileManager {
                 use KeyCode::*;
                 match key.code {
                     Char('q') => return Ok(true),
+                    Char('Q') => {
+                        self.set_cwd_file(None);
+                        return Ok(true);
+                    }
                     Char('j') => self.navigate_down().await,
                     Char('k') => self.navigate_up().await,
                     Char('d') => self.toggle_confirmation_popup(),
+                    Char('b') => self.bulk_rename_with_editor().await,
+                    Char('r') => self.start_pattern_rename(),
+                    Char('e') => self.edit_selected().await,
+                    Char(':') | Char('!') => self.start_shell_prompt(),
+                    Enter if self.chooser().is_some() => return Ok(self.choose_selected().await),
+                    Char('A') => self.git_stage().await,
+                    Char('U') => self.git_unstage().await,
+                    Char('X') => self.git_restore_prompt(),
+                    Char('z') => self.size_selected_dirs(),
+                    Char('P') => self.open_permission_editor(),
+                    Char('o') => self.open_ownership_editor(),
                     Esc => self.set_mode(InteractionMode::Normal),
                     _ => {}
                 }
@@ -116,3 +513,47 @@ impl FileManager {
         Ok(false)
     }
 }
+
+// Popups with a text field; what is typed there stays out of crash reports.
+fn takes_text(popup: &PopupType) -> bool {
+    matches!(
+        popup,
+        PopupType::Rename
+            | PopupType::Create
+            | PopupType::Search
+            | PopupType::Filter
+            | PopupType::Grep
+            | PopupType::PatternRename
+            | PopupType::Shell
+            | PopupType::Permissions
+            | PopupType::Ownership
+            | PopupType::Symlink
+    )
+}
+
+fn is_typing(key: &KeyEvent) -> bool {
+    matches!(key.code, KeyCode::Char(_) | KeyCode::Backspace)
+        && !key
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+}
+
+// Keys whose actions write to the filesystem; they are refused in read-only mode.
+fn modifies_files(mode: &InteractionMode, code: KeyCode) -> bool {
+    use KeyCode::Char;
+    // The editor, shell commands and subshells can change anything.
+    if matches!(code, Char('e' | ':' | '!' | 'T')) {
+        return true;
+    }
+    match mode {
+        InteractionMode::Normal => {
+            matches!(
+                code,
+                Char('d' | 'r' | 'a' | 'p' | '>' | '@' | 'b' | 'A' | 'U' | 'X' | 'P' | 'o')
+            )
+        }
+        InteractionMode::MultiSelect => {
+            matches!(code, Char('d' | 'r' | 'b' | 'A' | 'U' | 'X' | 'P' | 'o'))
+        }
+    }
+}
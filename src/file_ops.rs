@@ -22,6 +22,7 @@ impl FileManager {
             }
 
             path.push(name);
+            self.invalidate_dir_sizes(&path);
 
             if is_directory {
                 self.create_directory(path).await;
@@ -57,12 +58,12 @@ impl FileManager {
         if let Some(entry) = self.get_selected_index_entry() {
             let path = entry.entry_path().clone();
             let result = match entry.entry_type() {
-                FsEntryType::File => fs::remove_file(&path).await,
                 FsEntryType::Directory => fs::remove_dir_all(&path).await,
-                FsEntryType::Symlink => fs::remove_file(&path).await,
+                _ => fs::remove_file(&path).await,
             };
 
             if result.is_ok() {
+                self.invalidate_dir_sizes(&path);
                 self.set_popup(PopupType::None);
                 self.refresh_current_directory(self.current_path().clone())
                     .await;
@@ -74,18 +75,28 @@ impl FileManager {
     }
 
     pub async fn delete_multiple(&mut self) {
+        let mut errors = Vec::new();
         for path in self.get_selected_paths() {
-            let _ = if path.is_file() {
-                fs::remove_file(path).await
-            } else {
-                fs::remove_dir_all(path).await
+            self.invalidate_dir_sizes(&path);
+            // Only real directories are walked; links, FIFOs, sockets and devices are unlinked.
+            let result = match fs::symlink_metadata(&path).await {
+                Ok(meta) if meta.is_dir() => fs::remove_dir_all(&path).await,
+                Ok(_) => fs::remove_file(&path).await,
+                Err(e) => Err(e),
             };
+            if let Err(e) = result {
+                errors.push(format!("Failed to delete {path:?}: {e}"));
+            }
         }
 
         self.refresh_current_directory(self.current_path().clone())
             .await;
-        self.toggle_confirmation_popup();
+        // Toggling would leave the popup open once the listing is empty.
+        self.set_popup(PopupType::None);
         self.set_mode(InteractionMode::Normal);
+        if !errors.is_empty() {
+            self.show_notification(errors.join("\n"));
+        }
     }
 
     pub async fn rename_selected(&mut self, input: &mut str) {
@@ -94,6 +105,8 @@ impl FileManager {
             let new_path = self.current_path().join(input.trim_end_matches('/'));
 
             if fs::rename(old_path, &new_path).await.is_ok() {
+                self.invalidate_dir_sizes(old_path);
+                self.invalidate_dir_sizes(&new_path);
                 self.refresh_current_directory(self.current_path().clone())
                     .await;
                 self.mut_input_buffer().clear();
@@ -107,11 +120,14 @@ impl FileManager {
         if let Some(entry) = self.get_selected_index_entry() {
             let filepath = entry.entry_path().to_owned();
 
-            if entry
+            let is_zip = entry
                 .mime_type()
                 .clone()
-                .is_some_and(|mime| mime.subtype() == "zip")
-            {
+                .is_some_and(|mime| mime.subtype() == "zip");
+
+            if !is_zip {
+                self.open_default().await;
+            } else if self.ensure_writable() {
                 let result = tokio::task::spawn_blocking(move || {
                     let file = std::fs::File::open(&filepath)?;
                     let mut archive = ZipArchive::new(file)?;
@@ -123,6 +139,8 @@ impl FileManager {
                 match result {
                     Ok(inner) => match inner {
                         Ok(_) => {
+                            let current = self.current_path().clone();
+                            self.invalidate_dir_sizes(&current);
                             self.refresh_current_directory(self.current_path().clone())
                                 .await;
                             self.show_notification("✅ Zip extracted".to_string());
@@ -0,0 +1,348 @@
+use crate::modals::{FileContent, FileManager, GitInfo, GitStatus, PopupType, PreviewContent};
+use ratatui::style::Color;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::process::Command;
+
+impl GitStatus {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Self::Ignored => "!",
+            Self::Untracked => "?",
+            Self::Staged => "+",
+            Self::Modified => "M",
+            Self::Conflicted => "U",
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            Self::Ignored => Color::DarkGray,
+            Self::Untracked => Color::Magenta,
+            Self::Staged => Color::Green,
+            Self::Modified => Color::Yellow,
+            Self::Conflicted => Color::Red,
+        }
+    }
+}
+
+impl GitInfo {
+    // Directories report the most severe status below them.
+    pub fn status(&self, path: &Path) -> Option<GitStatus> {
+        if let Some(status) = self
+            .statuses()
+            .get(path)
+            .or_else(|| self.rollup().get(path))
+        {
+            return Some(*status);
+        }
+        // Untracked and ignored directories are listed once, not file by file.
+        path.ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(self.root()))
+            .filter_map(|dir| self.statuses().get(dir))
+            .find(|status| matches!(status, GitStatus::Untracked | GitStatus::Ignored))
+            .copied()
+    }
+
+    pub fn branch_label(&self) -> String {
+        let mut label = self.branch().unwrap_or("(no branch)").to_string();
+        if self.ahead() > 0 {
+            label.push_str(&format!(" ↑{}", self.ahead()));
+        }
+        if self.behind() > 0 {
+            label.push_str(&format!(" ↓{}", self.behind()));
+        }
+        label
+    }
+}
+
+// Stdout on success, otherwise the first line git printed to stderr.
+async fn git_output(dir: &Path, args: &[&str], paths: &[PathBuf]) -> Result<Vec<u8>, String> {
+    let mut command = Command::new("git");
+    command.arg("-C").arg(dir).args(args);
+    if !paths.is_empty() {
+        command.arg("--").args(paths);
+    }
+    let output = command
+        // Don't take the index lock behind the user's back.
+        .env("GIT_OPTIONAL_LOCKS", "0")
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|e| format!("git: {e}"))?;
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(stderr.lines().next().unwrap_or("git failed").to_string())
+    }
+}
+
+// None outside a repository or when git is not installed.
+async fn load_git_info(dir: PathBuf) -> Option<GitInfo> {
+    let toplevel = git_output(&dir, &["rev-parse", "--show-toplevel"], &[])
+        .await
+        .ok()?;
+    let root = PathBuf::from(OsStr::from_bytes(toplevel.trim_ascii_end()));
+    let args = ["status", "--porcelain=v2", "--branch", "-z", "--ignored"];
+    let output = git_output(&root, &args, &[]).await.ok()?;
+    Some(parse_status(root, &output))
+}
+
+// Index status first, then the worktree; a file changed in both shows as modified.
+fn xy_status(xy: &[u8]) -> Option<GitStatus> {
+    match xy {
+        [_, y, ..] if *y != b'.' => Some(GitStatus::Modified),
+        [x, ..] if *x != b'.' => Some(GitStatus::Staged),
+        _ => None,
+    }
+}
+
+fn parse_status(root: PathBuf, output: &[u8]) -> GitInfo {
+    let mut branch = None;
+    let (mut ahead, mut behind) = (0, 0);
+    let mut statuses = HashMap::new();
+    let mut records = output.split(|&b| b == 0);
+
+    while let Some(record) = records.next() {
+        let fields = |n| record.splitn(n, |&b| b == b' ').collect::<Vec<_>>();
+        let (status, path) = match record.first() {
+            Some(b'#') => {
+                let header = String::from_utf8_lossy(record);
+                if let Some(head) = header.strip_prefix("# branch.head ") {
+                    branch = Some(head.to_string());
+                } else if let Some(counts) = header.strip_prefix("# branch.ab ") {
+                    for count in counts.split_whitespace() {
+                        match count.split_at(1) {
+                            ("+", n) => ahead = n.parse().unwrap_or(0),
+                            ("-", n) => behind = n.parse().unwrap_or(0),
+                            _ => {}
+                        }
+                    }
+                }
+                continue;
+            }
+            Some(b'1') => {
+                let fields = fields(9);
+                (
+                    fields.get(1).and_then(|xy| xy_status(xy)),
+                    fields.get(8).copied(),
+                )
+            }
+            Some(b'2') => {
+                let fields = fields(10);
+                // The original path of a rename follows as its own record.
+                records.next();
+                (
+                    fields.get(1).and_then(|xy| xy_status(xy)),
+                    fields.get(9).copied(),
+                )
+            }
+            Some(b'u') => (Some(GitStatus::Conflicted), fields(11).get(10).copied()),
+            Some(b'?') => (Some(GitStatus::Untracked), record.get(2..)),
+            Some(b'!') => (Some(GitStatus::Ignored), record.get(2..)),
+            _ => continue,
+        };
+        if let (Some(status), Some(path)) = (status, path) {
+            let path = path.strip_suffix(b"/").unwrap_or(path);
+            statuses.insert(root.join(OsStr::from_bytes(path)), status);
+        }
+    }
+
+    let mut rollup: HashMap<PathBuf, GitStatus> = HashMap::new();
+    for (path, status) in &statuses {
+        if *status == GitStatus::Ignored {
+            continue;
+        }
+        for dir in path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(&root))
+        {
+            let current = rollup.entry(dir.to_path_buf()).or_insert(*status);
+            *current = (*current).max(*status);
+        }
+    }
+
+    GitInfo::new(root, branch, ahead, behind, statuses, rollup)
+}
+
+impl FileManager {
+    // Runs `git status` in the background; results from older requests are dropped.
+    pub fn refresh_git_status(&mut self) {
+        let generation = self.git_generation() + 1;
+        self.set_git_generation(generation);
+        let tx = self.git_tx().clone();
+        let dir = self.current_path().clone();
+        tokio::spawn(async move {
+            let _ = tx.send((generation, load_git_info(dir).await));
+        });
+    }
+
+    pub fn handle_git_status(&mut self, (generation, info): (u64, Option<GitInfo>)) {
+        if generation == self.git_generation() {
+            self.set_git(info);
+        }
+    }
+}
+
+impl FileManager {
+    // Targets inside the current repository, or a notification explaining why there are none.
+    fn git_action_targets(&mut self) -> Option<Vec<PathBuf>> {
+        if self.git().is_none() {
+            self.show_notification("Not inside a git repository".to_string());
+            return None;
+        }
+        let paths = self.selected_or_hovered_paths();
+        (!paths.is_empty()).then_some(paths)
+    }
+
+    async fn run_git_action(&mut self, args: &[&str], paths: &[PathBuf], done: &str) -> bool {
+        let dir = self.current_path().clone();
+        match git_output(&dir, args, paths).await {
+            Ok(_) => {
+                self.show_notification(format!("{done} {} path(s)", paths.len()));
+                true
+            }
+            Err(e) => {
+                self.show_notification(e);
+                false
+            }
+        }
+    }
+
+    pub async fn git_stage(&mut self) {
+        if let Some(paths) = self.git_action_targets() {
+            self.run_git_action(&["add"], &paths, "Staged").await;
+            self.refresh_git_status();
+        }
+    }
+
+    // `reset` rather than `restore --staged`, so it also works before the first commit.
+    pub async fn git_unstage(&mut self) {
+        if let Some(paths) = self.git_action_targets() {
+            self.run_git_action(&["reset", "-q"], &paths, "Unstaged")
+                .await;
+            self.refresh_git_status();
+        }
+    }
+
+    pub fn git_restore_prompt(&mut self) {
+        if let Some(paths) = self.git_action_targets() {
+            self.set_git_restore(paths);
+            self.set_popup(PopupType::GitRestore);
+        }
+    }
+
+    // Discards staged and unstaged changes, bringing the files back to HEAD.
+    pub async fn apply_git_restore(&mut self) {
+        let paths = self.take_git_restore();
+        self.set_popup(PopupType::None);
+        let args = ["restore", "--source=HEAD", "--staged", "--worktree"];
+        self.run_git_action(&args, &paths, "Restored").await;
+        for path in &paths {
+            self.invalidate_dir_sizes(path);
+        }
+        self.reload_current_directory().await;
+    }
+
+    pub fn cancel_git_restore(&mut self) {
+        self.take_git_restore();
+        self.set_popup(PopupType::None);
+    }
+
+    // Unstaged changes of the hovered file; moving the cursor brings the normal preview back.
+    pub async fn git_diff_preview(&mut self) {
+        if self.git().is_none() {
+            self.show_notification("Not inside a git repository".to_string());
+            return;
+        }
+        let Some(path) = self
+            .get_selected_index_entry_unmut()
+            .map(|entry| entry.entry_path().clone())
+        else {
+            return;
+        };
+        let dir = self.current_path().clone();
+        match git_output(&dir, &["diff", "--no-color", "--no-ext-diff"], &[path]).await {
+            Ok(diff) if diff.is_empty() => {
+                self.show_notification("No unstaged changes".to_string())
+            }
+            Ok(diff) => {
+                let text = String::from_utf8_lossy(&diff).to_string();
+                self.set_preview(PreviewContent::File(FileContent::Diff(text)));
+            }
+            Err(e) => self.show_notification(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `git status --porcelain=v2 --branch -z --ignored`, with the NULs written as `|`.
+    const STATUS: &str = "# branch.oid 52de0707231f9978486fafc5fc39b134f60864be|\
+        # branch.head main|\
+        # branch.upstream origin/main|\
+        # branch.ab +2 -1|\
+        2 R. N... 100644 100644 100644 6178079 6178079 R100 docs/new name.md|? old.md|\
+        1 M. N... 100644 100644 100644 f2ad6c7 975fbec keep.txt|\
+        1 .M N... 100644 100644 100644 7898192 7898192 src/my file.rs|\
+        1 MM N... 100644 100644 100644 7898192 975fbec src/both.rs|\
+        u UU N... 100644 100644 100644 100644 f2ad6c7 975fbec 7898192 src/deep/merge me.rs|\
+        ? new file.txt|\
+        ! build/|";
+
+    fn parse(output: &str) -> GitInfo {
+        parse_status(PathBuf::from("/repo"), output.replace('|', "\0").as_bytes())
+    }
+
+    #[test]
+    fn reads_the_branch_and_upstream_counts() {
+        let info = parse(STATUS);
+        assert_eq!(info.branch(), Some("main"));
+        assert_eq!((info.ahead(), info.behind()), (2, 1));
+
+        let info = parse("# branch.oid (initial)|# branch.head (detached)|");
+        assert_eq!(info.branch(), Some("(detached)"));
+        assert_eq!((info.ahead(), info.behind()), (0, 0));
+    }
+
+    #[test]
+    fn reads_every_record_type() {
+        let info = parse(STATUS);
+        let status = |path: &str| info.statuses().get(&Path::new("/repo").join(path)).copied();
+
+        assert_eq!(status("docs/new name.md"), Some(GitStatus::Staged));
+        // The original path of the rename is not a record of its own.
+        assert_eq!(status("? old.md"), None);
+        assert_eq!(status("old.md"), None);
+        assert_eq!(status("keep.txt"), Some(GitStatus::Staged));
+        assert_eq!(status("src/my file.rs"), Some(GitStatus::Modified));
+        assert_eq!(status("src/both.rs"), Some(GitStatus::Modified));
+        assert_eq!(status("src/deep/merge me.rs"), Some(GitStatus::Conflicted));
+        assert_eq!(status("new file.txt"), Some(GitStatus::Untracked));
+        assert_eq!(status("build"), Some(GitStatus::Ignored));
+        assert_eq!(info.statuses().len(), 7);
+    }
+
+    #[test]
+    fn rolls_statuses_up_to_directories() {
+        let info = parse(STATUS);
+        let rollup = |path: &str| info.rollup().get(Path::new(path)).copied();
+
+        assert_eq!(rollup("/repo/docs"), Some(GitStatus::Staged));
+        assert_eq!(rollup("/repo/src/deep"), Some(GitStatus::Conflicted));
+        assert_eq!(rollup("/repo/src"), Some(GitStatus::Conflicted));
+        assert_eq!(rollup("/repo"), Some(GitStatus::Conflicted));
+        // Ignored entries don't mark their parents, and nothing outside the repository is.
+        assert_eq!(rollup("/"), None);
+        let info = parse("! build/|! cache/tmp/|");
+        assert!(info.rollup().is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+use crate::ignore_rules::IgnoreRules;
+use crate::modals::{
+    FileManager, FilterKind, GrepEvent, GrepMatch, GrepState, ListOptions, PopupType,
+};
+use crate::search::NameMatcher;
+use ratatui::widgets::ListState;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::sync::mpsc::{self, error::TryRecvError, UnboundedReceiver, UnboundedSender};
+use tokio::task::JoinSet;
+
+const MAX_FILE_SIZE: u64 = 4 * 1024 * 1024;
+const MAX_RESULTS: usize = 5000;
+const MAX_SNIPPET_LEN: usize = 200;
+// Files with a NUL byte in their first block are treated as binary and skipped.
+const BINARY_SNIFF_LEN: usize = 8192;
+
+// Walks `root` and greps every regular file on a bounded pool of blocking workers. Hidden
+// and ignored entries are skipped like in the listing, so every match can be jumped to.
+// Dropping the returned receiver cancels the walk and all workers.
+pub fn spawn_grep(
+    root: PathBuf,
+    matcher: NameMatcher,
+    options: ListOptions,
+) -> UnboundedReceiver<GrepEvent> {
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        let workers = std::thread::available_parallelism().map_or(4, |n| n.get());
+        let mut tasks = JoinSet::new();
+        let mut todo = vec![root];
+
+        while let Some(dir) = todo.pop() {
+            if tx.is_closed() {
+                break;
+            }
+            let Ok(mut rd) = fs::read_dir(&dir).await else {
+                continue;
+            };
+            let ignore_rules = options.respect_ignore().then(|| IgnoreRules::load(&dir));
+            while let Ok(Some(entry)) = rd.next_entry().await {
+                if !options.show_hidden() && entry.file_name().as_encoded_bytes().starts_with(b".")
+                {
+                    continue;
+                }
+                let Ok(ty) = entry.file_type().await else {
+                    continue;
+                };
+                if ignore_rules
+                    .as_ref()
+                    .is_some_and(|rules| rules.is_ignored(&entry.path(), ty.is_dir()))
+                {
+                    continue;
+                }
+                if ty.is_dir() {
+                    if entry.file_name() != ".git" {
+                        todo.push(entry.path());
+                    }
+                } else if ty.is_file() {
+                    while tasks.len() >= workers {
+                        tasks.join_next().await;
+                    }
+                    let (tx, matcher, path) = (tx.clone(), matcher.clone(), entry.path());
+                    tasks.spawn_blocking(move || search_file(&path, &matcher, &tx));
+                }
+            }
+        }
+
+        while tasks.join_next().await.is_some() {}
+        let _ = tx.send(GrepEvent::Done);
+    });
+
+    rx
+}
+
+fn search_file(path: &Path, matcher: &NameMatcher, tx: &UnboundedSender<GrepEvent>) {
+    if tx.is_closed() {
+        return;
+    }
+    match std::fs::metadata(path) {
+        Ok(meta) if meta.len() <= MAX_FILE_SIZE => {}
+        _ => return,
+    }
+    let Ok(bytes) = std::fs::read(path) else {
+        return;
+    };
+    let _ = tx.send(GrepEvent::Scanned);
+
+    if bytes[..bytes.len().min(BINARY_SNIFF_LEN)].contains(&0) {
+        return;
+    }
+
+    let text = String::from_utf8_lossy(&bytes);
+    for (index, line) in text.lines().enumerate() {
+        if matcher.is_match(line) {
+            let snippet: String = line.trim().chars().take(MAX_SNIPPET_LEN).collect();
+            let found = GrepMatch::new(path.to_path_buf(), index + 1, snippet);
+            if tx.send(GrepEvent::Match(found)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+impl FileManager {
+    pub fn start_content_search(&mut self) {
+        self.mut_input_buffer().clear();
+        self.set_popup(PopupType::Grep);
+    }
+
+    pub fn run_content_search(&mut self) {
+        let query = self.input_buffer().clone();
+        if query.is_empty() {
+            return;
+        }
+
+        match NameMatcher::new(&query, FilterKind::Substring) {
+            Ok(matcher) => {
+                let root = self.current_path().clone();
+                let receiver = spawn_grep(root.clone(), matcher, *self.list_options());
+                self.set_grep(Some(GrepState::new(query, root, receiver)));
+                self.mut_input_buffer().clear();
+                self.set_popup(PopupType::GrepResults);
+            }
+            Err(e) => self.show_notification(e),
+        }
+    }
+
+    // Applies `event` and drains whatever else the workers produced, so a busy search is
+    // redrawn once per batch. `None` means the workers are gone.
+    pub fn handle_grep_event(&mut self, event: Option<GrepEvent>) {
+        let Some(grep) = self.grep_mut() else {
+            return;
+        };
+
+        let mut next = event;
+        loop {
+            match next {
+                Some(GrepEvent::Scanned) => grep.add_scanned(),
+                Some(GrepEvent::Match(found)) => {
+                    grep.results_mut().push(found);
+                    if grep.results().len() >= MAX_RESULTS {
+                        grep.stop();
+                    }
+                }
+                Some(GrepEvent::Done) | None => grep.stop(),
+            }
+            let Some(receiver) = grep.receiver_mut() else {
+                return;
+            };
+            next = match receiver.try_recv() {
+                Ok(event) => Some(event),
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => None,
+            };
+        }
+    }
+
+    pub fn grep_navigate(&mut self, down: bool) {
+        if let Some(grep) = self.grep_mut() {
+            let len = grep.results().len();
+            if len == 0 {
+                return;
+            }
+            let current = grep.selection().selected().unwrap_or(0);
+            let next = if down {
+                (current + 1) % len
+            } else {
+                (current + len - 1) % len
+            };
+            grep.selection_mut().select(Some(next));
+        }
+    }
+
+    pub async fn open_grep_result(&mut self) {
+        let Some(found) = self.grep().and_then(|grep| {
+            grep.selection()
+                .selected()
+                .and_then(|index| grep.results().get(index))
+                .cloned()
+        }) else {
+            return;
+        };
+        let Some(parent) = found.path().parent().map(Path::to_path_buf) else {
+            return;
+        };
+
+        self.set_filter(None);
+        self.set_popup(PopupType::None);
+        if !self.refresh_current_directory(parent).await {
+            return;
+        }
+        // The listing options may have changed since the search ran.
+        let Some(index) = self
+            .entries()
+            .iter()
+            .position(|entry| entry.entry_path() == found.path())
+        else {
+            self.show_notification(format!("{} is not listed", found.path().display()));
+            return;
+        };
+        self.set_selection(ListState::default().with_selected(Some(index)));
+        self.refresh_preview().await;
+        self.update_parent_selection();
+        self.set_preview_line(Some(found.line()));
+    }
+
+    pub fn close_content_search(&mut self) {
+        self.set_grep(None);
+        self.set_popup(PopupType::None);
+    }
+}
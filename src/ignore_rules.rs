@@ -0,0 +1,56 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::path::Path;
+
+// `.ignore` / `.gitignore` rules that apply to one directory, collected from the
+// directory itself up to the root of its git repository (or `/` outside of one).
+pub struct IgnoreRules {
+    // Deepest directory first, so the closest rule decides.
+    matchers: Vec<Gitignore>,
+}
+
+impl IgnoreRules {
+    pub fn load(dir: &Path) -> Self {
+        let mut matchers = Vec::new();
+
+        for ancestor in dir.ancestors() {
+            // `.ignore` takes precedence over `.gitignore` in the same directory.
+            for name in [".ignore", ".gitignore"] {
+                if let Some(matcher) = build_matcher(ancestor, &ancestor.join(name)) {
+                    matchers.push(matcher);
+                }
+            }
+
+            let git_dir = ancestor.join(".git");
+            if git_dir.exists() {
+                if let Some(matcher) = build_matcher(ancestor, &git_dir.join("info/exclude")) {
+                    matchers.push(matcher);
+                }
+                break;
+            }
+        }
+
+        Self { matchers }
+    }
+
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        for matcher in &self.matchers {
+            match matcher.matched_path_or_any_parents(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+}
+
+fn build_matcher(root: &Path, file: &Path) -> Option<Gitignore> {
+    if !file.is_file() {
+        return None;
+    }
+    let mut builder = GitignoreBuilder::new(root);
+    // Invalid lines are skipped; the rest of the file still applies.
+    let _ = builder.add(file);
+    builder.build().ok().filter(|matcher| !matcher.is_empty())
+}
@@ -0,0 +1,147 @@
+use crate::modals::{FileManager, PopupType};
+use crate::permissions::format_mode;
+use crate::utils::format_size;
+use chrono::{DateTime, Local};
+use mime_guess::Mime;
+use std::fs::{self, FileType};
+use std::io;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use uzers::{get_group_by_gid, get_user_by_uid};
+
+fn file_kind(file_type: &FileType) -> (char, &'static str) {
+    if file_type.is_symlink() {
+        ('l', "symbolic link")
+    } else if file_type.is_dir() {
+        ('d', "directory")
+    } else if file_type.is_fifo() {
+        ('p', "named pipe")
+    } else if file_type.is_socket() {
+        ('s', "socket")
+    } else if file_type.is_block_device() {
+        ('b', "block device")
+    } else if file_type.is_char_device() {
+        ('c', "character device")
+    } else {
+        ('-', "regular file")
+    }
+}
+
+fn format_time(time: Option<SystemTime>) -> String {
+    match time {
+        Some(time) => DateTime::<Local>::from(time)
+            .format("%Y-%m-%d %H:%M:%S %z")
+            .to_string(),
+        None => "unavailable".to_string(),
+    }
+}
+
+// Linux encoding of `dev_t`, as in glibc's `major()` / `minor()`.
+fn major_minor(dev: u64) -> String {
+    let major = ((dev >> 8) & 0xfff) | ((dev >> 32) & !0xfff);
+    let minor = (dev & 0xff) | ((dev >> 12) & !0xff);
+    format!("{major}:{minor}")
+}
+
+// Label/value rows for the info popup; the entry itself is never followed if it is a link.
+pub fn entry_info(path: &Path, mime: Option<&Mime>) -> io::Result<Vec<(String, String)>> {
+    let metadata = fs::symlink_metadata(path)?;
+    let file_type = metadata.file_type();
+    let (kind_char, kind) = file_kind(&file_type);
+    let mut rows = vec![
+        ("Path", path.display().to_string()),
+        ("Type", kind.to_string()),
+    ];
+
+    if file_type.is_symlink() {
+        let target = fs::read_link(path)?;
+        let broken = if path.exists() { "" } else { " (broken)" };
+        rows.push(("Target", format!("{}{broken}", target.display())));
+    } else {
+        let canonical =
+            fs::canonicalize(path).map_or_else(|e| e.to_string(), |p| p.display().to_string());
+        rows.push(("Canonical", canonical));
+    }
+
+    let user = get_user_by_uid(metadata.uid())
+        .map_or("?".to_string(), |u| u.name().to_string_lossy().to_string());
+    let group = get_group_by_gid(metadata.gid())
+        .map_or("?".to_string(), |g| g.name().to_string_lossy().to_string());
+    let changed = UNIX_EPOCH
+        + Duration::new(
+            metadata.ctime().max(0) as u64,
+            metadata.ctime_nsec().clamp(0, 999_999_999) as u32,
+        );
+
+    rows.extend([
+        (
+            "Size",
+            format!("{} ({} bytes)", format_size(metadata.len()), metadata.len()),
+        ),
+        (
+            "Mode",
+            format!(
+                "{kind_char}{} ({:04o})",
+                format_mode(metadata.mode()),
+                metadata.mode() & 0o7777
+            ),
+        ),
+        ("Owner", format!("{user} ({})", metadata.uid())),
+        ("Group", format!("{group} ({})", metadata.gid())),
+        ("Inode", metadata.ino().to_string()),
+        ("Links", metadata.nlink().to_string()),
+        ("Device", major_minor(metadata.dev())),
+        (
+            "Blocks",
+            format!(
+                "{} ({} on disk, {} byte I/O blocks)",
+                metadata.blocks(),
+                format_size(metadata.blocks() * 512),
+                metadata.blksize()
+            ),
+        ),
+    ]);
+    if file_type.is_block_device() || file_type.is_char_device() {
+        rows.push(("Device ID", major_minor(metadata.rdev())));
+    }
+    rows.extend([
+        ("Accessed", format_time(metadata.accessed().ok())),
+        ("Modified", format_time(metadata.modified().ok())),
+        ("Changed", format_time(Some(changed))),
+        // Some filesystems report a zero birth time instead of none.
+        (
+            "Born",
+            format_time(metadata.created().ok().filter(|t| *t != UNIX_EPOCH)),
+        ),
+        (
+            "Mime",
+            mime.map_or("unknown".to_string(), |mime| mime.to_string()),
+        ),
+    ]);
+
+    Ok(rows
+        .into_iter()
+        .map(|(label, value)| (label.to_string(), value))
+        .collect())
+}
+
+impl FileManager {
+    pub fn open_info(&mut self) {
+        let Some(entry) = self.get_selected_index_entry_unmut() else {
+            return;
+        };
+        match entry_info(entry.entry_path(), entry.mime_type().as_ref()) {
+            Ok(rows) => {
+                self.set_info(rows);
+                self.set_popup(PopupType::Info);
+            }
+            Err(e) => self.show_notification(e.to_string()),
+        }
+    }
+
+    pub fn close_info(&mut self) {
+        self.set_info(Vec::new());
+        self.set_popup(PopupType::None);
+    }
+}
@@ -0,0 +1,152 @@
+use crate::modals::{Action, FileManager, NewSymlink, PopupType, SymlinkField};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use tokio::fs;
+
+// `to` as seen from the directory `from`; both must be absolute and free of symlinks.
+fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let from: Vec<Component> = from.components().collect();
+    let to: Vec<Component> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+    let mut path = PathBuf::new();
+    for _ in common..from.len() {
+        path.push("..");
+    }
+    for component in &to[common..] {
+        path.push(component);
+    }
+    if path.as_os_str().is_empty() {
+        path.push(".");
+    }
+    path
+}
+
+// Resolves the directories above `path` but not `path` itself, which may be a link.
+async fn resolve_parent(path: &Path) -> io::Result<PathBuf> {
+    let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+        return Ok(path.to_path_buf());
+    };
+    Ok(fs::canonicalize(parent).await?.join(name))
+}
+
+async fn create_link(action: &Action, src: &Path, dst: &Path) -> io::Result<()> {
+    match action {
+        Action::Symlink => fs::symlink(src, dst).await,
+        Action::RelativeSymlink => {
+            let src = resolve_parent(src).await?;
+            let dir = resolve_parent(dst)
+                .await?
+                .parent()
+                .map_or_else(|| PathBuf::from("/"), Path::to_path_buf);
+            fs::symlink(relative_path(&dir, &src), dst).await
+        }
+        Action::Hardlink => fs::hard_link(src, dst).await.map_err(|e| {
+            if e.kind() == io::ErrorKind::CrossesDevices {
+                io::Error::new(e.kind(), "hard links cannot cross filesystems")
+            } else {
+                e
+            }
+        }),
+        Action::Move | Action::Copy | Action::None => Ok(()),
+    }
+}
+
+impl FileManager {
+    pub fn open_paste_link(&mut self) {
+        if self.clipboard().get_path().is_empty() {
+            self.show_notification("Nothing to link; yank with y or x first".to_string());
+            return;
+        }
+        self.set_popup(PopupType::PasteLink);
+    }
+
+    // Links every clipboard entry into the current directory, whatever it was yanked for.
+    pub async fn paste_links(&mut self, action: Action) {
+        self.set_popup(PopupType::None);
+        let paths = self.clipboard().get_path().clone();
+        let mut errors = Vec::new();
+        for src in &paths {
+            let Some(name) = src.file_name() else {
+                continue;
+            };
+            let dst = self.current_path().join(name);
+            match create_link(&action, src, &dst).await {
+                Ok(()) => self.invalidate_dir_sizes(&dst),
+                Err(e) => errors.push(format!("{}: {e}", dst.display())),
+            }
+        }
+
+        self.refresh_current_directory(self.current_path().clone())
+            .await;
+        self.set_clipboard_actions(Action::None);
+        if errors.is_empty() {
+            self.show_notification(format!("Created {} link(s)", paths.len()));
+        } else {
+            self.show_notification(errors.join("\n"));
+        }
+    }
+
+    pub fn start_symlink(&mut self) {
+        self.set_new_symlink(Some(NewSymlink::default()));
+        self.set_popup(PopupType::Symlink);
+    }
+
+    pub fn symlink_focus(&mut self) {
+        if let Some(symlink) = self.new_symlink_mut() {
+            let focus = match symlink.focus() {
+                SymlinkField::Target => SymlinkField::Name,
+                SymlinkField::Name => SymlinkField::Target,
+            };
+            symlink.set_focus(focus);
+        }
+    }
+
+    pub fn symlink_key(&mut self, c: char) {
+        if let Some(symlink) = self.new_symlink_mut() {
+            symlink.focused_input_mut().push(c);
+        }
+    }
+
+    pub fn symlink_backspace(&mut self) {
+        if let Some(symlink) = self.new_symlink_mut() {
+            symlink.focused_input_mut().pop();
+        }
+    }
+
+    // The target is stored as typed, so a relative one stays relative to the link.
+    pub async fn apply_symlink(&mut self) {
+        let Some(symlink) = self.new_symlink().cloned() else {
+            return;
+        };
+        let target = PathBuf::from(symlink.target());
+        let name = if symlink.name().is_empty() {
+            target.file_name().map(PathBuf::from)
+        } else {
+            Some(PathBuf::from(symlink.name()))
+        };
+        let Some(name) = name.filter(|name| name.file_name() == Some(name.as_os_str())) else {
+            self.show_notification("Enter a target and a link name".to_string());
+            return;
+        };
+
+        let dst = self.current_path().join(name);
+        if let Err(e) = fs::symlink(&target, &dst).await {
+            self.show_notification(format!("{}: {e}", dst.display()));
+            return;
+        }
+        self.cancel_symlink();
+        self.invalidate_dir_sizes(&dst);
+        self.refresh_current_directory(self.current_path().clone())
+            .await;
+        self.focus_path(&dst).await;
+        if !dst.exists() {
+            self.show_notification(format!("{} points to a missing target", dst.display()));
+        }
+    }
+
+    pub fn cancel_symlink(&mut self) {
+        self.set_new_symlink(None);
+        self.set_popup(PopupType::None);
+    }
+}
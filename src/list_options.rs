@@ -0,0 +1,69 @@
+use crate::modals::{FileManager, SortMode, SortOptions};
+
+impl SortMode {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Natural => Self::Size,
+            Self::Size => Self::Modified,
+            Self::Modified => Self::Extension,
+            Self::Extension => Self::Type,
+            Self::Type => Self::Natural,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Natural => "name",
+            Self::Size => "size",
+            Self::Modified => "modified",
+            Self::Extension => "extension",
+            Self::Type => "type",
+        }
+    }
+}
+
+impl SortOptions {
+    pub fn label(&self) -> String {
+        let arrow = if self.reverse() { "↓" } else { "↑" };
+        let dirs = if self.dirs_first() {
+            ", dirs first"
+        } else {
+            ""
+        };
+        format!("{} {arrow}{dirs}", self.mode().label())
+    }
+}
+
+impl FileManager {
+    pub async fn cycle_sort_mode(&mut self) {
+        let mode = self.sort().mode().next();
+        self.sort_mut().set_mode(mode);
+        self.reload_current_directory().await;
+    }
+
+    pub async fn toggle_sort_reverse(&mut self) {
+        let reverse = !self.sort().reverse();
+        self.sort_mut().set_reverse(reverse);
+        self.reload_current_directory().await;
+    }
+
+    pub async fn toggle_dirs_first(&mut self) {
+        let dirs_first = !self.sort().dirs_first();
+        self.sort_mut().set_dirs_first(dirs_first);
+        self.reload_current_directory().await;
+    }
+
+    pub async fn toggle_hidden(&mut self) {
+        let show_hidden = !self.list_options().show_hidden();
+        self.list_options_mut().set_show_hidden(show_hidden);
+        self.reload_current_directory().await;
+        self.update_parent_selection();
+    }
+
+    pub async fn toggle_ignore_rules(&mut self) {
+        let respect_ignore = !self.list_options().respect_ignore();
+        self.list_options_mut().set_respect_ignore(respect_ignore);
+        self.reload_current_directory().await;
+        self.update_parent_selection();
+    }
+}
@@ -1,12 +1,34 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use ratatui::widgets::ListState;
 use std::path::{Path, PathBuf};
 use tokio::fs;
+mod analyzer;
+mod bulk_rename;
+mod chooser;
+mod cli;
+mod config;
+mod crash;
+mod disk_usage;
 mod event_handler;
 mod file_ops;
+mod git;
+mod grep;
+mod ignore_rules;
+mod inspect;
+mod links;
+mod list_options;
+mod opener;
+mod ownership;
+mod permissions;
+mod search;
+mod shell;
+mod terminal;
 mod ui;
 mod utils;
-use utils::{copy_dir_iterative, get_state_data, move_file};
+use cli::CliCommand;
+use config::Config;
+use modals::{Chooser, ListOptions};
+use utils::{copy_dir_iterative, get_state_data, listing_error, move_file};
 mod modals;
 use crate::modals::{
     Action, FileContent, FileManager, FsEntry, FsEntryType, InteractionMode, PopupType,
@@ -14,18 +36,47 @@ use crate::modals::{
 };
 
 impl FileManager {
-    async fn refresh_current_directory(&mut self, new_path: PathBuf) {
-        match get_state_data(&new_path).await {
+    // Returns false, with a notification, when `new_path` can't be listed; nothing changes then.
+    async fn refresh_current_directory(&mut self, new_path: PathBuf) -> bool {
+        match get_state_data(&new_path, self.list_options()).await {
             Ok((entries, parent_path, parent_entries)) => {
+                if new_path != *self.current_path() {
+                    self.set_filter(None);
+                    self.set_search_query(None);
+                }
                 self.set_current_path(new_path);
                 self.set_entries(entries);
                 self.parent_view_mut().set_path(parent_path);
-                self.parent_view_mut().set_entries(parent_entries);
+                self.parent_view_mut().set_listing(parent_entries);
+                // Listings are refreshed after every file operation, so the status follows.
+                self.refresh_git_status();
+                true
+            }
+            Err(e) => {
+                self.show_notification(e.to_string());
+                false
             }
-            Err(e) => self.show_notification(e.to_string()),
         }
     }
 
+    // Re-lists the current directory, keeping the cursor and the marks on entries that still exist.
+    async fn reload_current_directory(&mut self) {
+        let hovered = self
+            .get_selected_index_entry_unmut()
+            .map(|entry| entry.entry_path().clone());
+        let marked = self.get_selected_paths();
+        self.refresh_current_directory(self.current_path().clone())
+            .await;
+        for entry in self.entries_mut() {
+            entry.is_selected = marked.contains(entry.entry_path());
+        }
+        let index = hovered
+            .and_then(|path| self.entries().iter().position(|e| e.entry_path() == &path))
+            .unwrap_or(0);
+        self.selection_mut().select(Some(index));
+        self.refresh_preview().await;
+    }
+
     async fn refresh_preview_with_directory(&mut self, items: Vec<FsEntry>) {
         self.set_preview(PreviewContent::Directory(items));
         self.update_parent_selection();
@@ -73,12 +124,17 @@ impl FileManager {
 
     async fn paste_clipboard(&mut self) {
         let clipboard = self.clipboard().clone();
+        let mut skipped = Vec::new();
         for src in clipboard.get_path() {
             let dst = self.current_path().join(src.file_name().unwrap());
+            self.invalidate_dir_sizes(src);
+            self.invalidate_dir_sizes(&dst);
             if src.is_file() {
                 match self.clipboard_actions() {
+                    // The copy is a side effect, so it stays out of a match guard.
+                    #[allow(clippy::collapsible_match)]
                     Action::Move => {
-                        if fs::copy(src, dst).await.is_ok() {
+                        if fs::copy(src, &dst).await.is_ok() {
                             if let Err(e) = fs::remove_file(src).await {
                                 self.show_notification(e.to_string())
                             }
@@ -105,10 +161,16 @@ impl FileManager {
                     }
                     _ => {}
                 }
+            } else {
+                // FIFOs, sockets, devices and dangling links have no contents to copy.
+                skipped.push(src.file_name().unwrap().to_string_lossy().to_string());
             }
         }
         self.refresh_current_directory(self.current_path().clone())
             .await;
+        if !skipped.is_empty() {
+            self.show_notification(format!("Skipped special files: {}", skipped.join(", ")));
+        }
         self.set_clipboard_actions(Action::None)
     }
 
@@ -120,26 +182,57 @@ impl FileManager {
             .collect()
     }
 
+    // The marked entries, or the hovered one.
+    fn selected_or_hovered_paths(&self) -> Vec<PathBuf> {
+        let selected = self.get_selected_paths();
+        if !selected.is_empty() {
+            return selected;
+        }
+        self.get_selected_index_entry_unmut()
+            .map(|entry| vec![entry.entry_path().clone()])
+            .unwrap_or_default()
+    }
+
     async fn refresh_preview(&mut self) {
         if let Some(entry) = self.get_selected_index_entry() {
             let path = entry.entry_path().clone();
             match entry.entry_type() {
-                FsEntryType::Directory => match utils::list_dir(&path).await {
+                FsEntryType::Directory => match utils::list_dir(&path, self.list_options()).await {
                     Ok(items) => self.refresh_preview_with_directory(items).await,
-                    Err(e) => self.show_notification(e.to_string()),
+                    Err(e) => self.set_preview(PreviewContent::Unavailable(listing_error(&e))),
                 },
 
-                FsEntryType::File => match utils::read_valid_file(&path).await {
-                    Ok(text) => self.refresh_preview_with_text_file(text).await,
-                    Err(e) => self.refresh_preview_with_binary_file(e.to_string()),
-                },
+                FsEntryType::File | FsEntryType::Executable => {
+                    match utils::read_valid_file(&path).await {
+                        Ok(text) => self.refresh_preview_with_text_file(text).await,
+                        Err(e) => self.refresh_preview_with_binary_file(e.to_string()),
+                    }
+                }
+
+                FsEntryType::Fifo
+                | FsEntryType::Socket
+                | FsEntryType::BlockDevice
+                | FsEntryType::CharDevice => {
+                    let label = entry.entry_type().special_label().unwrap_or_default();
+                    self.refresh_preview_with_binary_file(format!("{label}, not previewed"));
+                }
+
+                FsEntryType::Unknown => {
+                    let reason = fs::symlink_metadata(&path)
+                        .await
+                        .err()
+                        .map_or("metadata unavailable".to_string(), |e| listing_error(&e));
+                    self.set_preview(PreviewContent::Unavailable(reason));
+                }
 
                 FsEntryType::Symlink => {
                     if let Some(target_path) = self.symlink_resolver(&path).await {
                         if target_path.is_dir() {
-                            match utils::list_dir(&target_path).await {
+                            match utils::list_dir(&target_path, self.list_options()).await {
                                 Ok(items) => self.refresh_preview_with_directory(items).await,
-                                Err(e) => self.show_notification(e.to_string()),
+                                Err(e) => {
+                                    self.set_preview(PreviewContent::Unavailable(listing_error(&e)))
+                                }
                             }
                         } else {
                             match utils::read_valid_file(&target_path).await {
@@ -195,7 +288,7 @@ impl FileManager {
     }
 
     fn update_parent_selection(&mut self) {
-        if let Some(current_name) = self.current_path().file_name().map(|n| n.to_string_lossy()) {
+        if let Some(current_name) = self.current_path().file_name().map(|n| n.to_os_string()) {
             if let Some(index) = self
                 .parent_view()
                 .entries()
@@ -210,7 +303,9 @@ impl FileManager {
 
     async fn navigate_to_parent(&mut self) {
         if let Some(ref parent_path) = self.parent_view().path() {
-            self.refresh_current_directory(parent_path.clone()).await;
+            if !self.refresh_current_directory(parent_path.clone()).await {
+                return;
+            }
             self.set_selection(self.parent_view().selection().clone());
             self.refresh_preview().await;
         }
@@ -248,19 +343,21 @@ impl FileManager {
         let selection = self.selection().clone();
         if let Some(entry) = self.get_selected_index_entry_unmut() {
             if entry.entry_type() == &FsEntryType::Directory {
-                let mut path = self.current_path().clone();
-                path.push(entry.name());
-                self.refresh_current_directory(path).await;
+                let path = entry.entry_path().clone();
+                if !self.refresh_current_directory(path).await {
+                    return;
+                }
                 self.parent_view_mut().set_selection(selection);
                 self.set_selection(ListState::default().with_selected(Some(0)));
                 self.refresh_preview().await;
             } else if entry.entry_type() == &FsEntryType::Symlink {
-                let mut path = self.current_path().clone();
-                path.push(entry.name());
+                let path = entry.entry_path().clone();
 
                 if let Some(target_path) = self.symlink_resolver(&path).await {
                     if target_path.is_dir() {
-                        self.refresh_current_directory(target_path).await;
+                        if !self.refresh_current_directory(target_path).await {
+                            return;
+                        }
                         self.parent_view_mut().set_selection(selection);
                         self.set_selection(ListState::default().with_selected(Some(0)));
                         self.refresh_preview().await;
@@ -285,6 +382,27 @@ impl FileManager {
         self.set_notify(message);
     }
 
+    // Returns false, with a notification, when started with --read-only.
+    fn ensure_writable(&mut self) -> bool {
+        if self.read_only() {
+            self.show_notification("Read-only mode".to_string());
+        }
+        !self.read_only()
+    }
+
+    // Puts the cursor on `path` if it is listed in the current directory.
+    async fn focus_path(&mut self, path: &Path) {
+        if let Some(index) = self.entries().iter().position(|e| e.entry_path() == path) {
+            self.selection_mut().select(Some(index));
+            self.refresh_preview().await;
+        }
+    }
+
+    fn notification_deadline(&self) -> Option<std::time::Instant> {
+        self.notify()
+            .map(|notify| notify.created_at() + notify.duration())
+    }
+
     fn clear_expired_notifications(&mut self) {
         if let Some(notify) = self.notify() {
             if notify.created_at().elapsed() >= notify.duration() {
@@ -307,15 +425,102 @@ impl FileManager {
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    let terminal = ratatui::init();
+async fn main() {
+    let args = match cli::parse(std::env::args_os().skip(1)) {
+        Ok(CliCommand::Run(args)) => args,
+        Ok(CliCommand::Help) => {
+            print!("{}", cli::HELP);
+            return;
+        }
+        Ok(CliCommand::Version) => {
+            println!("arbor {}", env!("CARGO_PKG_VERSION"));
+            return;
+        }
+        Ok(CliCommand::ShellInit(wrapper)) => {
+            print!("{wrapper}");
+            return;
+        }
+        Err(e) => {
+            eprintln!("arbor: {e}\nTry 'arbor --help' for more information.");
+            std::process::exit(2);
+        }
+    };
 
-    let start_dir = PathBuf::from(".");
-    let absolute_path = start_dir.canonicalize().expect("Failed to resolve path");
+    if let Err(e) = start(args).await {
+        eprintln!("arbor: {e:#}");
+        std::process::exit(1);
+    }
+}
 
-    let fm = FileManager::new(&absolute_path).await?;
-    let result = fm.run(terminal).await;
+async fn start(args: cli::CliArgs) -> Result<()> {
+    // Resolved before the terminal is taken over so errors reach the shell.
+    let start = args.path.clone().unwrap_or_else(|| PathBuf::from("."));
+    let resolved = start
+        .canonicalize()
+        .with_context(|| format!("Cannot open {}", start.display()))?;
+    let (start_dir, focus) = if resolved.is_dir() {
+        (resolved, None)
+    } else {
+        let parent = resolved.parent().map(Path::to_path_buf).unwrap_or_default();
+        (parent, Some(resolved))
+    };
+
+    // An explicitly given config must exist and parse; the default one only gets a notification.
+    let (config, config_error) = match &args.config {
+        Some(path) if !path.exists() => anyhow::bail!("Config {} not found", path.display()),
+        Some(path) => (Config::load(path)?, None),
+        None => match Config::default_path().map(|p| Config::load(&p)) {
+            Some(Err(e)) => (Config::default(), Some(e)),
+            loaded => (loaded.and_then(Result::ok).unwrap_or_default(), None),
+        },
+    };
+
+    let mut list_options = ListOptions::default();
+    // A dotfile given on the command line has to be listed to put the cursor on it.
+    let focus_hidden = focus
+        .as_ref()
+        .and_then(|path| path.file_name())
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+    list_options.set_show_hidden(args.show_hidden || focus_hidden);
+    if let Some(mode) = args.sort {
+        list_options.sort_mut().set_mode(mode);
+    }
 
-    ratatui::restore();
-    result
+    let chooser = args.chooser_file.as_ref().map(|file| {
+        // `-` sends the chosen paths to stdout.
+        let output = (file.as_os_str() != "-").then(|| file.clone());
+        let separator = if args.null { b'\0' } else { b'\n' };
+        Chooser::new(output, separator, args.choose_dirs, !args.single)
+    });
+
+    let mut fm = FileManager::new(&start_dir, config, list_options)
+        .await
+        .with_context(|| format!("Cannot list {}", start_dir.display()))?;
+    fm.set_read_only(args.read_only);
+    fm.set_chooser(chooser);
+    fm.set_cwd_file(args.cwd_file.clone());
+    if let Some(path) = focus {
+        fm.focus_path(&path).await;
+    }
+    if let Some(e) = config_error {
+        fm.show_notification(format!("{e:#}"));
+    }
+    crash::record_path(fm.current_path());
+
+    let mut terminal = terminal::init()?;
+    let result = fm.run(&mut terminal).await;
+
+    terminal::suspend()?;
+    result?;
+    // Written once the screen is restored, so stdout only carries the paths.
+    if let Some(chooser) = fm.chooser() {
+        chooser
+            .write()
+            .context("Failed to write the chosen paths")?;
+    }
+    if let Some(file) = fm.cwd_file() {
+        std::fs::write(file, fm.current_path().as_os_str().as_encoded_bytes())
+            .with_context(|| format!("Failed to write {}", file.display()))?;
+    }
+    Ok(())
 }
@@ -1,38 +1,64 @@
+use crate::bulk_rename::RenamePreviewRow;
+use crate::config::{Config, OpenerRule};
 use crate::get_state_data;
+use crate::search::NameMatcher;
+use crate::utils::listing_error;
+use crossterm::event::EventStream;
 use mime_guess::Mime;
 use ratatui::widgets::ListState;
+use std::collections::{HashMap, VecDeque};
+use std::ffi::{OsStr, OsString};
 use std::path::PathBuf;
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
 use std::time::Duration;
 use std::time::Instant;
+use std::time::SystemTime;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::oneshot;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum FsEntryType {
     File,
+    Executable,
     Directory,
     Symlink,
+    Fifo,
+    Socket,
+    BlockDevice,
+    CharDevice,
+    // Listed, but its metadata could not be read.
+    Unknown,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct FsEntry {
-    name: String,
+    name: OsString,
+    // Lossy UTF-8 form of `name`, for display and matching.
+    display_name: String,
     entry_path: PathBuf,
     entry_type: FsEntryType,
     size: u64,
     file_permission: u32,
     pub is_selected: bool,
     mime: Option<Mime>,
+    modified: Option<SystemTime>,
+    // Set when someone other than the current user owns the entry.
+    owner: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 pub enum FileContent {
     Text(String),
     Binary(String),
+    Diff(String),
 }
 
 #[derive(Debug, Clone)]
 pub enum PreviewContent {
     File(FileContent),
     Directory(Vec<FsEntry>),
+    Unavailable(String),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -40,9 +66,242 @@ pub enum PopupType {
     Confirm,
     Rename,
     Create,
+    Search,
+    Filter,
+    Grep,
+    GrepResults,
+    BulkRename,
+    PatternRename,
+    OpenWith,
+    Shell,
+    ShellLog,
+    GitRestore,
+    Analyzer,
+    Info,
+    Permissions,
+    Ownership,
+    PasteLink,
+    Symlink,
     None,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShellMode {
+    Foreground,
+    Background,
+}
+
+#[derive(Debug)]
+pub enum ShellEvent {
+    Output(usize, String),
+    Exited(usize, Option<i32>),
+}
+
+#[derive(Debug, Clone)]
+pub struct ShellJob {
+    command: String,
+    output: VecDeque<String>,
+    running: bool,
+    exit_code: Option<i32>,
+}
+
+// Ordered by severity; directories show the highest status below them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GitStatus {
+    Ignored,
+    Untracked,
+    Staged,
+    Modified,
+    Conflicted,
+}
+
+// Status of the repository containing `current_path`, keyed by absolute path.
+#[derive(Debug, Clone)]
+pub struct GitInfo {
+    root: PathBuf,
+    branch: Option<String>,
+    ahead: u32,
+    behind: u32,
+    statuses: HashMap<PathBuf, GitStatus>,
+    rollup: HashMap<PathBuf, GitStatus>,
+}
+
+// One entry of a disk usage scan; directories carry the totals of everything below them.
+#[derive(Debug, Clone)]
+pub struct DuNode {
+    path: PathBuf,
+    is_dir: bool,
+    apparent: u64,
+    disk: u64,
+    files: u64,
+    children: Vec<DuNode>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnalyzerAction {
+    Delete,
+    Trash,
+}
+
+#[derive(Debug)]
+pub struct Analyzer {
+    root: Option<DuNode>,
+    current: PathBuf,
+    selection: ListState,
+    use_disk: bool,
+    scanned: Arc<AtomicU64>,
+    receiver: Option<oneshot::Receiver<DuNode>>,
+    confirm: Option<AnalyzerAction>,
+    changed: bool,
+}
+
+// Picker mode: the chosen paths are written to `output` (stdout when `None`) on exit.
+#[derive(Debug, Clone)]
+pub struct Chooser {
+    output: Option<PathBuf>,
+    separator: u8,
+    dirs_only: bool,
+    multiple: bool,
+    chosen: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub struct OpenWith {
+    paths: Vec<PathBuf>,
+    rules: Vec<OpenerRule>,
+    selection: ListState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CaseConversion {
+    Keep,
+    Lower,
+    Upper,
+    Title,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PatternField {
+    Find,
+    Replace,
+    Case,
+    Extension,
+}
+
+#[derive(Debug, Clone)]
+pub struct PatternRename {
+    sources: Vec<PathBuf>,
+    find: String,
+    replace: String,
+    case: CaseConversion,
+    extension: String,
+    focus: PatternField,
+    // Recomputed when an input changes, never while drawing.
+    preview: Result<Vec<RenamePreviewRow>, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PermissionField {
+    Grid,
+    Expression,
+    Recursive,
+}
+
+// Which entries below a directory a permission change reaches.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecursiveScope {
+    Off,
+    All,
+    Directories,
+    Files,
+}
+
+// `mode` is what the grid shows; a non-empty `expression` is applied to each target instead.
+#[derive(Debug, Clone)]
+pub struct PermissionEditor {
+    targets: Vec<PathBuf>,
+    base: u32,
+    base_is_dir: bool,
+    mode: u32,
+    cursor: (usize, usize),
+    expression: String,
+    recursive: RecursiveScope,
+    focus: PermissionField,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OwnershipField {
+    Owner,
+    Group,
+    Recursive,
+}
+
+// An empty owner or group is left unchanged. `users` and `groups` are the completion
+// candidates.
+#[derive(Debug, Clone)]
+pub struct OwnershipEditor {
+    targets: Vec<PathBuf>,
+    current: String,
+    owner: String,
+    group: String,
+    recursive: RecursiveScope,
+    focus: OwnershipField,
+    users: Vec<String>,
+    groups: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SymlinkField {
+    #[default]
+    Target,
+    Name,
+}
+
+// The link is created in the current directory; an empty name takes the target's.
+#[derive(Debug, Clone, Default)]
+pub struct NewSymlink {
+    target: String,
+    name: String,
+    focus: SymlinkField,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortMode {
+    Natural,
+    Size,
+    Modified,
+    Extension,
+    Type,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SortOptions {
+    mode: SortMode,
+    reverse: bool,
+    dirs_first: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ListOptions {
+    sort: SortOptions,
+    show_hidden: bool,
+    respect_ignore: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FilterKind {
+    Substring,
+    Glob,
+    Regex,
+}
+
+#[derive(Debug, Clone)]
+pub struct Filter {
+    pattern: String,
+    kind: FilterKind,
+    matcher: NameMatcher,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum InteractionMode {
     Normal,
@@ -60,6 +319,9 @@ pub struct Notification {
 pub enum Action {
     Move,
     Copy,
+    Symlink,
+    RelativeSymlink,
+    Hardlink,
     None,
 }
 
@@ -68,10 +330,35 @@ pub struct Clipboard {
     paths: Vec<PathBuf>,
     action: Action,
 }
-#[derive(Clone, Debug)]
+#[derive(Debug, Clone)]
+pub struct GrepMatch {
+    path: PathBuf,
+    line: usize,
+    snippet: String,
+}
+
+#[derive(Debug)]
+pub enum GrepEvent {
+    Scanned,
+    Match(GrepMatch),
+    Done,
+}
+
+#[derive(Debug)]
+pub struct GrepState {
+    query: String,
+    root: PathBuf,
+    results: Vec<GrepMatch>,
+    selection: ListState,
+    receiver: Option<UnboundedReceiver<GrepEvent>>,
+    files_scanned: usize,
+}
+
+#[derive(Debug)]
 pub struct FileManager {
     parent_view: ParentView,
     current_path: PathBuf,
+    all_entries: Vec<FsEntry>,
     entries: Vec<FsEntry>,
     preview: PreviewContent,
     selection: ListState,
@@ -80,6 +367,53 @@ pub struct FileManager {
     clipboard: Clipboard,
     input_buffer: String,
     popup: PopupType,
+    filter: Option<Filter>,
+    filter_kind: FilterKind,
+    search_query: Option<String>,
+    search_origin: Option<usize>,
+    grep: Option<GrepState>,
+    preview_line: Option<usize>,
+    list_options: ListOptions,
+    pending_renames: Vec<(PathBuf, PathBuf)>,
+    pattern_rename: Option<PatternRename>,
+    full_redraw: bool,
+    config: Config,
+    open_with: Option<OpenWith>,
+    shell_mode: ShellMode,
+    shell_jobs: Vec<ShellJob>,
+    shell_log_selection: ListState,
+    shell_events_tx: UnboundedSender<ShellEvent>,
+    shell_events_rx: UnboundedReceiver<ShellEvent>,
+    read_only: bool,
+    chooser: Option<Chooser>,
+    cwd_file: Option<PathBuf>,
+    git: Option<GitInfo>,
+    git_generation: u64,
+    git_tx: UnboundedSender<(u64, Option<GitInfo>)>,
+    git_rx: UnboundedReceiver<(u64, Option<GitInfo>)>,
+    git_restore: Vec<PathBuf>,
+    dir_sizes: HashMap<PathBuf, u64>,
+    // The generation of the scan whose result is awaited for each path.
+    dir_sizes_pending: HashMap<PathBuf, u64>,
+    dir_size_generation: u64,
+    dir_size_tx: UnboundedSender<(u64, PathBuf, u64)>,
+    dir_size_rx: UnboundedReceiver<(u64, PathBuf, u64)>,
+    analyzer: Option<Analyzer>,
+    info: Vec<(String, String)>,
+    permission_editor: Option<PermissionEditor>,
+    ownership_editor: Option<OwnershipEditor>,
+    new_symlink: Option<NewSymlink>,
+    input: Option<EventStream>,
+}
+
+// Everything the event loop waits on, borrowed at once so a single `select!` can poll it.
+pub struct EventSources<'a> {
+    pub input: &'a mut EventStream,
+    pub shell: &'a mut UnboundedReceiver<ShellEvent>,
+    pub git: &'a mut UnboundedReceiver<(u64, Option<GitInfo>)>,
+    pub dir_sizes: &'a mut UnboundedReceiver<(u64, PathBuf, u64)>,
+    pub grep: Option<&'a mut UnboundedReceiver<GrepEvent>>,
+    pub analyzer: Option<&'a mut oneshot::Receiver<DuNode>>,
 }
 
 #[derive(Clone, Debug)]
@@ -87,19 +421,33 @@ pub struct ParentView {
     entries: Vec<FsEntry>,
     path: Option<PathBuf>,
     selection: ListState,
+    error: Option<String>,
 }
 
 impl FileManager {
-    pub async fn new(start_path: &PathBuf) -> Result<Self, std::io::Error> {
-        let (entries, parent_path, parent_entries) = get_state_data(start_path).await.unwrap();
+    pub async fn new(
+        start_path: &PathBuf,
+        config: Config,
+        list_options: ListOptions,
+    ) -> Result<Self, std::io::Error> {
+        let (shell_events_tx, shell_events_rx) = mpsc::unbounded_channel();
+        let (git_tx, git_rx) = mpsc::unbounded_channel();
+        let (dir_size_tx, dir_size_rx) = mpsc::unbounded_channel();
+        let (entries, parent_path, parent_entries) =
+            get_state_data(start_path, &list_options).await?;
+
+        let mut parent_view = ParentView {
+            path: parent_path,
+            entries: Vec::new(),
+            selection: ListState::default(),
+            error: None,
+        };
+        parent_view.set_listing(parent_entries);
 
         let mut state = Self {
-            parent_view: ParentView {
-                path: parent_path,
-                entries: parent_entries,
-                selection: ListState::default(),
-            },
+            parent_view,
             current_path: start_path.clone(),
+            all_entries: entries.clone(),
             entries,
             preview: PreviewContent::Directory(vec![]),
             selection: ListState::default().with_selected(Some(0)),
@@ -111,9 +459,46 @@ impl FileManager {
             },
             input_buffer: String::new(),
             popup: PopupType::None,
+            filter: None,
+            filter_kind: FilterKind::Substring,
+            search_query: None,
+            search_origin: None,
+            grep: None,
+            preview_line: None,
+            list_options,
+            pending_renames: Vec::new(),
+            pattern_rename: None,
+            full_redraw: false,
+            config,
+            open_with: None,
+            shell_mode: ShellMode::Foreground,
+            shell_jobs: Vec::new(),
+            shell_log_selection: ListState::default(),
+            shell_events_tx,
+            shell_events_rx,
+            read_only: false,
+            chooser: None,
+            cwd_file: None,
+            git: None,
+            git_generation: 0,
+            git_tx,
+            git_rx,
+            git_restore: Vec::new(),
+            dir_sizes: HashMap::new(),
+            dir_sizes_pending: HashMap::new(),
+            dir_size_generation: 0,
+            dir_size_tx,
+            dir_size_rx,
+            analyzer: None,
+            info: Vec::new(),
+            permission_editor: None,
+            ownership_editor: None,
+            new_symlink: None,
+            input: None,
         };
 
         state.refresh_preview().await;
+        state.refresh_git_status();
         state.update_parent_selection();
         Ok(state)
     }
@@ -122,7 +507,16 @@ impl FileManager {
         &self.entries
     }
     pub fn set_entries(&mut self, entry: Vec<FsEntry>) {
-        self.entries = entry
+        self.entries = self.filtered_entries(&entry);
+        self.all_entries = entry;
+    }
+
+    pub fn all_entries(&self) -> &[FsEntry] {
+        &self.all_entries
+    }
+
+    pub fn set_filtered_entries(&mut self, entries: Vec<FsEntry>) {
+        self.entries = entries
     }
 
     pub fn entries_mut(&mut self) -> &mut Vec<FsEntry> {
@@ -143,6 +537,14 @@ impl FileManager {
 
     pub fn set_preview(&mut self, preview: PreviewContent) {
         self.preview = preview;
+        self.preview_line = None;
+    }
+
+    pub fn preview_line(&self) -> Option<usize> {
+        self.preview_line
+    }
+    pub fn set_preview_line(&mut self, line: Option<usize>) {
+        self.preview_line = line
     }
 
     pub fn current_path(&self) -> &PathBuf {
@@ -220,6 +622,268 @@ impl FileManager {
     pub fn preview_mut(&self) -> &PreviewContent {
         &self.preview
     }
+
+    pub fn filter(&self) -> Option<&Filter> {
+        self.filter.as_ref()
+    }
+    pub fn set_filter(&mut self, filter: Option<Filter>) {
+        self.filter = filter
+    }
+
+    pub fn filter_kind(&self) -> FilterKind {
+        self.filter_kind
+    }
+    pub fn set_filter_kind(&mut self, kind: FilterKind) {
+        self.filter_kind = kind
+    }
+
+    pub fn search_query(&self) -> Option<&String> {
+        self.search_query.as_ref()
+    }
+    pub fn set_search_query(&mut self, query: Option<String>) {
+        self.search_query = query
+    }
+
+    pub fn search_origin(&self) -> Option<usize> {
+        self.search_origin
+    }
+    pub fn set_search_origin(&mut self, origin: Option<usize>) {
+        self.search_origin = origin
+    }
+
+    pub fn grep(&self) -> Option<&GrepState> {
+        self.grep.as_ref()
+    }
+    pub fn grep_mut(&mut self) -> Option<&mut GrepState> {
+        self.grep.as_mut()
+    }
+    pub fn set_grep(&mut self, grep: Option<GrepState>) {
+        self.grep = grep
+    }
+
+    pub fn list_options(&self) -> &ListOptions {
+        &self.list_options
+    }
+    pub fn list_options_mut(&mut self) -> &mut ListOptions {
+        &mut self.list_options
+    }
+
+    pub fn pending_renames(&self) -> &[(PathBuf, PathBuf)] {
+        &self.pending_renames
+    }
+    pub fn set_pending_renames(&mut self, plan: Vec<(PathBuf, PathBuf)>) {
+        self.pending_renames = plan
+    }
+    pub fn take_pending_renames(&mut self) -> Vec<(PathBuf, PathBuf)> {
+        std::mem::take(&mut self.pending_renames)
+    }
+
+    pub fn pattern_rename(&self) -> Option<&PatternRename> {
+        self.pattern_rename.as_ref()
+    }
+    pub fn pattern_rename_mut(&mut self) -> Option<&mut PatternRename> {
+        self.pattern_rename.as_mut()
+    }
+    pub fn set_pattern_rename(&mut self, rename: Option<PatternRename>) {
+        self.pattern_rename = rename
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn open_with_mut(&mut self) -> Option<&mut OpenWith> {
+        self.open_with.as_mut()
+    }
+    pub fn set_open_with(&mut self, menu: Option<OpenWith>) {
+        self.open_with = menu
+    }
+    pub fn take_open_with(&mut self) -> Option<OpenWith> {
+        self.open_with.take()
+    }
+    pub fn open_with(&self) -> Option<&OpenWith> {
+        self.open_with.as_ref()
+    }
+
+    pub fn shell_mode(&self) -> ShellMode {
+        self.shell_mode
+    }
+    pub fn set_shell_mode(&mut self, mode: ShellMode) {
+        self.shell_mode = mode
+    }
+
+    pub fn shell_jobs(&self) -> &[ShellJob] {
+        &self.shell_jobs
+    }
+    pub fn shell_jobs_mut(&mut self) -> &mut Vec<ShellJob> {
+        &mut self.shell_jobs
+    }
+
+    pub fn shell_log_selection(&self) -> &ListState {
+        &self.shell_log_selection
+    }
+    pub fn shell_log_selection_mut(&mut self) -> &mut ListState {
+        &mut self.shell_log_selection
+    }
+
+    pub fn shell_events_tx(&self) -> &UnboundedSender<ShellEvent> {
+        &self.shell_events_tx
+    }
+    pub fn shell_events_rx_mut(&mut self) -> &mut UnboundedReceiver<ShellEvent> {
+        &mut self.shell_events_rx
+    }
+
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only
+    }
+
+    pub fn chooser(&self) -> Option<&Chooser> {
+        self.chooser.as_ref()
+    }
+    pub fn chooser_mut(&mut self) -> Option<&mut Chooser> {
+        self.chooser.as_mut()
+    }
+    pub fn set_chooser(&mut self, chooser: Option<Chooser>) {
+        self.chooser = chooser
+    }
+
+    pub fn cwd_file(&self) -> Option<&PathBuf> {
+        self.cwd_file.as_ref()
+    }
+    pub fn set_cwd_file(&mut self, path: Option<PathBuf>) {
+        self.cwd_file = path
+    }
+
+    pub fn git(&self) -> Option<&GitInfo> {
+        self.git.as_ref()
+    }
+    pub fn set_git(&mut self, git: Option<GitInfo>) {
+        self.git = git
+    }
+    pub fn git_generation(&self) -> u64 {
+        self.git_generation
+    }
+    pub fn set_git_generation(&mut self, generation: u64) {
+        self.git_generation = generation
+    }
+    pub fn git_tx(&self) -> &UnboundedSender<(u64, Option<GitInfo>)> {
+        &self.git_tx
+    }
+
+    pub fn git_restore(&self) -> &[PathBuf] {
+        &self.git_restore
+    }
+    pub fn set_git_restore(&mut self, paths: Vec<PathBuf>) {
+        self.git_restore = paths
+    }
+    pub fn take_git_restore(&mut self) -> Vec<PathBuf> {
+        std::mem::take(&mut self.git_restore)
+    }
+
+    pub fn dir_sizes(&self) -> &HashMap<PathBuf, u64> {
+        &self.dir_sizes
+    }
+    pub fn dir_sizes_mut(&mut self) -> &mut HashMap<PathBuf, u64> {
+        &mut self.dir_sizes
+    }
+    pub fn dir_sizes_pending(&self) -> &HashMap<PathBuf, u64> {
+        &self.dir_sizes_pending
+    }
+    pub fn dir_sizes_pending_mut(&mut self) -> &mut HashMap<PathBuf, u64> {
+        &mut self.dir_sizes_pending
+    }
+    pub fn dir_size_generation(&self) -> u64 {
+        self.dir_size_generation
+    }
+    pub fn set_dir_size_generation(&mut self, generation: u64) {
+        self.dir_size_generation = generation
+    }
+    pub fn dir_size_tx(&self) -> &UnboundedSender<(u64, PathBuf, u64)> {
+        &self.dir_size_tx
+    }
+
+    pub fn analyzer(&self) -> Option<&Analyzer> {
+        self.analyzer.as_ref()
+    }
+    pub fn analyzer_mut(&mut self) -> Option<&mut Analyzer> {
+        self.analyzer.as_mut()
+    }
+    pub fn set_analyzer(&mut self, analyzer: Option<Analyzer>) {
+        self.analyzer = analyzer
+    }
+    pub fn take_analyzer(&mut self) -> Option<Analyzer> {
+        self.analyzer.take()
+    }
+
+    pub fn info(&self) -> &[(String, String)] {
+        &self.info
+    }
+    pub fn set_info(&mut self, rows: Vec<(String, String)>) {
+        self.info = rows
+    }
+    pub fn permission_editor(&self) -> Option<&PermissionEditor> {
+        self.permission_editor.as_ref()
+    }
+    pub fn permission_editor_mut(&mut self) -> Option<&mut PermissionEditor> {
+        self.permission_editor.as_mut()
+    }
+    pub fn set_permission_editor(&mut self, editor: Option<PermissionEditor>) {
+        self.permission_editor = editor
+    }
+    pub fn ownership_editor(&self) -> Option<&OwnershipEditor> {
+        self.ownership_editor.as_ref()
+    }
+    pub fn ownership_editor_mut(&mut self) -> Option<&mut OwnershipEditor> {
+        self.ownership_editor.as_mut()
+    }
+    pub fn set_ownership_editor(&mut self, editor: Option<OwnershipEditor>) {
+        self.ownership_editor = editor
+    }
+    pub fn new_symlink(&self) -> Option<&NewSymlink> {
+        self.new_symlink.as_ref()
+    }
+    pub fn new_symlink_mut(&mut self) -> Option<&mut NewSymlink> {
+        self.new_symlink.as_mut()
+    }
+    pub fn set_new_symlink(&mut self, symlink: Option<NewSymlink>) {
+        self.new_symlink = symlink
+    }
+
+    // The input stream is created on demand, so dropping it is enough to stop reading the terminal.
+    pub fn event_sources(&mut self) -> EventSources<'_> {
+        EventSources {
+            input: self.input.get_or_insert_with(EventStream::new),
+            shell: &mut self.shell_events_rx,
+            git: &mut self.git_rx,
+            dir_sizes: &mut self.dir_size_rx,
+            grep: self.grep.as_mut().and_then(|grep| grep.receiver.as_mut()),
+            analyzer: self
+                .analyzer
+                .as_mut()
+                .and_then(|analyzer| analyzer.receiver.as_mut()),
+        }
+    }
+    pub fn close_input(&mut self) {
+        self.input = None
+    }
+
+    // Set after handing the terminal to another program; the next draw repaints everything.
+    pub fn request_full_redraw(&mut self) {
+        self.full_redraw = true
+    }
+    pub fn take_full_redraw(&mut self) -> bool {
+        std::mem::take(&mut self.full_redraw)
+    }
+
+    pub fn sort(&self) -> &SortOptions {
+        self.list_options.sort()
+    }
+    pub fn sort_mut(&mut self) -> &mut SortOptions {
+        self.list_options.sort_mut()
+    }
 }
 
 impl FsEntry {
@@ -227,9 +891,12 @@ impl FsEntry {
     //     self.is_selected
     // }
 
-    pub fn name(&self) -> &str {
+    pub fn name(&self) -> &OsStr {
         &self.name
     }
+    pub fn display_name(&self) -> &str {
+        &self.display_name
+    }
     pub fn entry_type(&self) -> &FsEntryType {
         &self.entry_type
     }
@@ -245,6 +912,12 @@ impl FsEntry {
     pub fn mime_type(&self) -> &Option<Mime> {
         &self.mime
     }
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+    pub fn owner(&self) -> Option<&str> {
+        self.owner.as_deref()
+    }
 }
 
 impl Notification {
@@ -262,8 +935,22 @@ impl ParentView {
     pub fn entries(&self) -> &Vec<FsEntry> {
         &self.entries
     }
-    pub fn set_entries(&mut self, entries: Vec<FsEntry>) {
-        self.entries = entries
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    // An unreadable parent is shown as an empty pane carrying the reason.
+    pub fn set_listing(&mut self, listing: std::io::Result<Vec<FsEntry>>) {
+        match listing {
+            Ok(entries) => {
+                self.entries = entries;
+                self.error = None;
+            }
+            Err(e) => {
+                self.entries = Vec::new();
+                self.error = Some(listing_error(&e));
+            }
+        }
     }
 
     pub fn selection(&self) -> &ListState {
@@ -279,16 +966,20 @@ impl ParentView {
     // }
 }
 impl FsEntry {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
-        name: String,
+        name: OsString,
         entry_path: PathBuf,
         entry_type: FsEntryType,
         size: u64,
         file_permission: u32,
         is_selected: bool,
         mime: Option<Mime>,
+        modified: Option<SystemTime>,
+        owner: Option<String>,
     ) -> Self {
         Self {
+            display_name: name.to_string_lossy().to_string(),
             name,
             entry_path,
             entry_type,
@@ -296,6 +987,8 @@ impl FsEntry {
             file_permission,
             is_selected,
             mime,
+            modified,
+            owner,
         }
     }
 }
@@ -315,3 +1008,545 @@ impl Clipboard {
         self.paths = path
     }
 }
+impl Filter {
+    pub fn new(pattern: String, kind: FilterKind, matcher: NameMatcher) -> Self {
+        Self {
+            pattern,
+            kind,
+            matcher,
+        }
+    }
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+    pub fn kind(&self) -> FilterKind {
+        self.kind
+    }
+    pub fn matcher(&self) -> &NameMatcher {
+        &self.matcher
+    }
+}
+impl GrepMatch {
+    pub fn new(path: PathBuf, line: usize, snippet: String) -> Self {
+        Self {
+            path,
+            line,
+            snippet,
+        }
+    }
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+    pub fn line(&self) -> usize {
+        self.line
+    }
+    pub fn snippet(&self) -> &str {
+        &self.snippet
+    }
+}
+impl GrepState {
+    pub fn new(query: String, root: PathBuf, receiver: UnboundedReceiver<GrepEvent>) -> Self {
+        Self {
+            query,
+            root,
+            results: Vec::new(),
+            selection: ListState::default().with_selected(Some(0)),
+            receiver: Some(receiver),
+            files_scanned: 0,
+        }
+    }
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+    pub fn root(&self) -> &PathBuf {
+        &self.root
+    }
+    pub fn results(&self) -> &[GrepMatch] {
+        &self.results
+    }
+    pub fn results_mut(&mut self) -> &mut Vec<GrepMatch> {
+        &mut self.results
+    }
+    pub fn selection(&self) -> &ListState {
+        &self.selection
+    }
+    pub fn selection_mut(&mut self) -> &mut ListState {
+        &mut self.selection
+    }
+    pub fn receiver_mut(&mut self) -> Option<&mut UnboundedReceiver<GrepEvent>> {
+        self.receiver.as_mut()
+    }
+    pub fn stop(&mut self) {
+        self.receiver = None
+    }
+    pub fn is_running(&self) -> bool {
+        self.receiver.is_some()
+    }
+    pub fn files_scanned(&self) -> usize {
+        self.files_scanned
+    }
+    pub fn add_scanned(&mut self) {
+        self.files_scanned += 1
+    }
+}
+impl Default for SortOptions {
+    fn default() -> Self {
+        Self {
+            mode: SortMode::Natural,
+            reverse: false,
+            dirs_first: true,
+        }
+    }
+}
+impl SortOptions {
+    pub fn mode(&self) -> SortMode {
+        self.mode
+    }
+    pub fn set_mode(&mut self, mode: SortMode) {
+        self.mode = mode
+    }
+    pub fn reverse(&self) -> bool {
+        self.reverse
+    }
+    pub fn set_reverse(&mut self, reverse: bool) {
+        self.reverse = reverse
+    }
+    pub fn dirs_first(&self) -> bool {
+        self.dirs_first
+    }
+    pub fn set_dirs_first(&mut self, dirs_first: bool) {
+        self.dirs_first = dirs_first
+    }
+}
+impl ListOptions {
+    pub fn sort(&self) -> &SortOptions {
+        &self.sort
+    }
+    pub fn sort_mut(&mut self) -> &mut SortOptions {
+        &mut self.sort
+    }
+    pub fn show_hidden(&self) -> bool {
+        self.show_hidden
+    }
+    pub fn set_show_hidden(&mut self, show_hidden: bool) {
+        self.show_hidden = show_hidden
+    }
+    pub fn respect_ignore(&self) -> bool {
+        self.respect_ignore
+    }
+    pub fn set_respect_ignore(&mut self, respect_ignore: bool) {
+        self.respect_ignore = respect_ignore
+    }
+}
+impl PatternRename {
+    pub fn new(sources: Vec<PathBuf>) -> Self {
+        Self {
+            sources,
+            find: String::new(),
+            replace: String::new(),
+            case: CaseConversion::Keep,
+            extension: String::new(),
+            focus: PatternField::Find,
+            preview: Ok(Vec::new()),
+        }
+    }
+    pub fn sources(&self) -> &[PathBuf] {
+        &self.sources
+    }
+    pub fn find(&self) -> &str {
+        &self.find
+    }
+    pub fn replace(&self) -> &str {
+        &self.replace
+    }
+    pub fn case(&self) -> CaseConversion {
+        self.case
+    }
+    pub fn set_case(&mut self, case: CaseConversion) {
+        self.case = case
+    }
+    pub fn extension(&self) -> &str {
+        &self.extension
+    }
+    pub fn focus(&self) -> PatternField {
+        self.focus
+    }
+    pub fn set_focus(&mut self, focus: PatternField) {
+        self.focus = focus
+    }
+    // The text field under the cursor; `None` while the case selector is focused.
+    pub fn focused_input_mut(&mut self) -> Option<&mut String> {
+        match self.focus {
+            PatternField::Find => Some(&mut self.find),
+            PatternField::Replace => Some(&mut self.replace),
+            PatternField::Extension => Some(&mut self.extension),
+            PatternField::Case => None,
+        }
+    }
+    pub fn preview(&self) -> &Result<Vec<RenamePreviewRow>, String> {
+        &self.preview
+    }
+    pub fn set_preview(&mut self, preview: Result<Vec<RenamePreviewRow>, String>) {
+        self.preview = preview
+    }
+}
+impl PermissionEditor {
+    pub fn new(targets: Vec<PathBuf>, base: u32, base_is_dir: bool) -> Self {
+        Self {
+            targets,
+            base,
+            base_is_dir,
+            mode: base,
+            cursor: (0, 0),
+            expression: String::new(),
+            recursive: RecursiveScope::Off,
+            focus: PermissionField::Grid,
+        }
+    }
+    pub fn targets(&self) -> &[PathBuf] {
+        &self.targets
+    }
+    pub fn base(&self) -> u32 {
+        self.base
+    }
+    pub fn base_is_dir(&self) -> bool {
+        self.base_is_dir
+    }
+    pub fn mode(&self) -> u32 {
+        self.mode
+    }
+    pub fn set_mode(&mut self, mode: u32) {
+        self.mode = mode
+    }
+    pub fn cursor(&self) -> (usize, usize) {
+        self.cursor
+    }
+    pub fn set_cursor(&mut self, cursor: (usize, usize)) {
+        self.cursor = cursor
+    }
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+    pub fn expression_mut(&mut self) -> &mut String {
+        &mut self.expression
+    }
+    pub fn recursive(&self) -> RecursiveScope {
+        self.recursive
+    }
+    pub fn set_recursive(&mut self, recursive: RecursiveScope) {
+        self.recursive = recursive
+    }
+    pub fn focus(&self) -> PermissionField {
+        self.focus
+    }
+    pub fn set_focus(&mut self, focus: PermissionField) {
+        self.focus = focus
+    }
+}
+impl OwnershipEditor {
+    pub fn new(
+        targets: Vec<PathBuf>,
+        current: String,
+        users: Vec<String>,
+        groups: Vec<String>,
+    ) -> Self {
+        Self {
+            targets,
+            current,
+            owner: String::new(),
+            group: String::new(),
+            recursive: RecursiveScope::Off,
+            focus: OwnershipField::Owner,
+            users,
+            groups,
+        }
+    }
+    pub fn targets(&self) -> &[PathBuf] {
+        &self.targets
+    }
+    pub fn current(&self) -> &str {
+        &self.current
+    }
+    pub fn owner(&self) -> &str {
+        &self.owner
+    }
+    pub fn group(&self) -> &str {
+        &self.group
+    }
+    pub fn recursive(&self) -> RecursiveScope {
+        self.recursive
+    }
+    pub fn set_recursive(&mut self, recursive: RecursiveScope) {
+        self.recursive = recursive
+    }
+    pub fn focus(&self) -> OwnershipField {
+        self.focus
+    }
+    pub fn set_focus(&mut self, focus: OwnershipField) {
+        self.focus = focus
+    }
+    pub fn users(&self) -> &[String] {
+        &self.users
+    }
+    pub fn groups(&self) -> &[String] {
+        &self.groups
+    }
+    // The name field under the cursor; `None` while the recursive option is focused.
+    pub fn focused_input_mut(&mut self) -> Option<&mut String> {
+        match self.focus {
+            OwnershipField::Owner => Some(&mut self.owner),
+            OwnershipField::Group => Some(&mut self.group),
+            OwnershipField::Recursive => None,
+        }
+    }
+}
+impl NewSymlink {
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn focus(&self) -> SymlinkField {
+        self.focus
+    }
+    pub fn set_focus(&mut self, focus: SymlinkField) {
+        self.focus = focus
+    }
+    pub fn focused_input_mut(&mut self) -> &mut String {
+        match self.focus {
+            SymlinkField::Target => &mut self.target,
+            SymlinkField::Name => &mut self.name,
+        }
+    }
+}
+impl OpenWith {
+    pub fn new(paths: Vec<PathBuf>, rules: Vec<OpenerRule>) -> Self {
+        Self {
+            paths,
+            rules,
+            selection: ListState::default().with_selected(Some(0)),
+        }
+    }
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+    pub fn rules(&self) -> &[OpenerRule] {
+        &self.rules
+    }
+    pub fn selection(&self) -> &ListState {
+        &self.selection
+    }
+    pub fn selection_mut(&mut self) -> &mut ListState {
+        &mut self.selection
+    }
+}
+impl ShellJob {
+    pub fn new(command: String) -> Self {
+        Self {
+            command,
+            output: VecDeque::new(),
+            running: true,
+            exit_code: None,
+        }
+    }
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+    pub fn output(&self) -> &VecDeque<String> {
+        &self.output
+    }
+    pub fn output_mut(&mut self) -> &mut VecDeque<String> {
+        &mut self.output
+    }
+    pub fn running(&self) -> bool {
+        self.running
+    }
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+    pub fn finish(&mut self, exit_code: Option<i32>) {
+        self.running = false;
+        self.exit_code = exit_code;
+    }
+}
+
+impl Chooser {
+    pub fn new(output: Option<PathBuf>, separator: u8, dirs_only: bool, multiple: bool) -> Self {
+        Self {
+            output,
+            separator,
+            dirs_only,
+            multiple,
+            chosen: Vec::new(),
+        }
+    }
+    pub fn output(&self) -> Option<&PathBuf> {
+        self.output.as_ref()
+    }
+    pub fn separator(&self) -> u8 {
+        self.separator
+    }
+    pub fn dirs_only(&self) -> bool {
+        self.dirs_only
+    }
+    pub fn multiple(&self) -> bool {
+        self.multiple
+    }
+    pub fn chosen(&self) -> &[PathBuf] {
+        &self.chosen
+    }
+    pub fn set_chosen(&mut self, chosen: Vec<PathBuf>) {
+        self.chosen = chosen
+    }
+}
+
+impl GitInfo {
+    pub fn new(
+        root: PathBuf,
+        branch: Option<String>,
+        ahead: u32,
+        behind: u32,
+        statuses: HashMap<PathBuf, GitStatus>,
+        rollup: HashMap<PathBuf, GitStatus>,
+    ) -> Self {
+        Self {
+            root,
+            branch,
+            ahead,
+            behind,
+            statuses,
+            rollup,
+        }
+    }
+    pub fn root(&self) -> &PathBuf {
+        &self.root
+    }
+    pub fn branch(&self) -> Option<&str> {
+        self.branch.as_deref()
+    }
+    pub fn ahead(&self) -> u32 {
+        self.ahead
+    }
+    pub fn behind(&self) -> u32 {
+        self.behind
+    }
+    pub fn statuses(&self) -> &HashMap<PathBuf, GitStatus> {
+        &self.statuses
+    }
+    pub fn rollup(&self) -> &HashMap<PathBuf, GitStatus> {
+        &self.rollup
+    }
+}
+
+impl DuNode {
+    pub fn new(path: PathBuf, is_dir: bool, apparent: u64, disk: u64) -> Self {
+        Self {
+            path,
+            is_dir,
+            apparent,
+            disk,
+            files: u64::from(!is_dir),
+            children: Vec::new(),
+        }
+    }
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+    pub fn apparent(&self) -> u64 {
+        self.apparent
+    }
+    pub fn disk(&self) -> u64 {
+        self.disk
+    }
+    pub fn files(&self) -> u64 {
+        self.files
+    }
+    pub fn children(&self) -> &[DuNode] {
+        &self.children
+    }
+    pub fn children_mut(&mut self) -> &mut Vec<DuNode> {
+        &mut self.children
+    }
+    pub fn push(&mut self, child: DuNode) {
+        self.apparent += child.apparent;
+        self.disk += child.disk;
+        self.files += child.files;
+        self.children.push(child);
+    }
+    pub fn subtract(&mut self, removed: &DuNode) {
+        self.apparent = self.apparent.saturating_sub(removed.apparent);
+        self.disk = self.disk.saturating_sub(removed.disk);
+        self.files = self.files.saturating_sub(removed.files);
+    }
+}
+
+impl Analyzer {
+    pub fn new(
+        current: PathBuf,
+        scanned: Arc<AtomicU64>,
+        receiver: oneshot::Receiver<DuNode>,
+    ) -> Self {
+        Self {
+            root: None,
+            current,
+            selection: ListState::default().with_selected(Some(0)),
+            use_disk: true,
+            scanned,
+            receiver: Some(receiver),
+            confirm: None,
+            changed: false,
+        }
+    }
+    pub fn root(&self) -> Option<&DuNode> {
+        self.root.as_ref()
+    }
+    pub fn root_mut(&mut self) -> Option<&mut DuNode> {
+        self.root.as_mut()
+    }
+    pub fn set_root(&mut self, root: Option<DuNode>) {
+        self.root = root
+    }
+    pub fn current(&self) -> &PathBuf {
+        &self.current
+    }
+    pub fn set_current(&mut self, path: PathBuf) {
+        self.current = path
+    }
+    pub fn selection(&self) -> &ListState {
+        &self.selection
+    }
+    pub fn selection_mut(&mut self) -> &mut ListState {
+        &mut self.selection
+    }
+    pub fn use_disk(&self) -> bool {
+        self.use_disk
+    }
+    pub fn set_use_disk(&mut self, use_disk: bool) {
+        self.use_disk = use_disk
+    }
+    pub fn scanned(&self) -> &Arc<AtomicU64> {
+        &self.scanned
+    }
+    pub fn receiver(&self) -> Option<&oneshot::Receiver<DuNode>> {
+        self.receiver.as_ref()
+    }
+    pub fn set_receiver(&mut self, receiver: Option<oneshot::Receiver<DuNode>>) {
+        self.receiver = receiver
+    }
+    pub fn confirm(&self) -> Option<AnalyzerAction> {
+        self.confirm
+    }
+    pub fn set_confirm(&mut self, action: Option<AnalyzerAction>) {
+        self.confirm = action
+    }
+    pub fn changed(&self) -> bool {
+        self.changed
+    }
+    pub fn set_changed(&mut self, changed: bool) {
+        self.changed = changed
+    }
+}
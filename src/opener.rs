@@ -0,0 +1,179 @@
+use crate::config::OpenerRule;
+use crate::modals::{FileManager, FsEntryType, OpenWith, PopupType};
+use crate::terminal::editor_command;
+use globset::Glob;
+use mime_guess::Mime;
+use std::path::PathBuf;
+use std::process::Stdio;
+use tokio::process::Command;
+
+impl OpenerRule {
+    pub fn matches(&self, name: &str, mime: Option<&Mime>) -> bool {
+        if self.mime().is_none() && self.glob().is_none() {
+            return true;
+        }
+        let mime_match = self
+            .mime()
+            .is_some_and(|pattern| mime_matches(pattern, mime));
+        let glob_match = self.glob().is_some_and(|pattern| {
+            Glob::new(pattern).is_ok_and(|glob| glob.compile_matcher().is_match(name))
+        });
+        mime_match || glob_match
+    }
+}
+
+// Accepts `*`, `*/*`, `type/*` or a full `type/subtype`.
+fn mime_matches(pattern: &str, mime: Option<&Mime>) -> bool {
+    if pattern == "*" || pattern == "*/*" {
+        return true;
+    }
+    let Some(mime) = mime else {
+        return false;
+    };
+    match pattern.split_once('/') {
+        Some((top, "*")) => mime.type_() == top,
+        _ => mime.essence_str() == pattern,
+    }
+}
+
+// Without any `$` in the command the files are appended, so `command = "mpv"` just works.
+fn shell_command(rule: &OpenerRule, paths: &[PathBuf]) -> Command {
+    let mut script = rule.command().to_string();
+    if !script.contains('$') {
+        script.push_str(r#" "$@""#);
+    }
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(script).arg("arbor").args(paths);
+    command
+}
+
+impl FileManager {
+    // The marked entries, or the hovered one. Directories are never handed to openers.
+    fn open_targets(&self) -> Vec<PathBuf> {
+        let selected = self.get_selected_paths();
+        let paths = if selected.is_empty() {
+            self.get_selected_index_entry_unmut()
+                .map(|entry| vec![entry.entry_path().clone()])
+                .unwrap_or_default()
+        } else {
+            selected
+        };
+        paths.into_iter().filter(|path| !path.is_dir()).collect()
+    }
+
+    fn matching_openers(&self) -> Vec<OpenerRule> {
+        let Some(entry) = self.get_selected_index_entry_unmut() else {
+            return Vec::new();
+        };
+        if *entry.entry_type() == FsEntryType::Directory {
+            return Vec::new();
+        }
+        // Terminal openers are usually editors, so read-only mode leaves only detached ones.
+        self.config()
+            .openers()
+            .iter()
+            .filter(|rule| !(self.read_only() && rule.terminal()))
+            .filter(|rule| rule.matches(entry.display_name(), entry.mime_type().as_ref()))
+            .cloned()
+            .collect()
+    }
+
+    pub async fn open_default(&mut self) {
+        let paths = self.open_targets();
+        if paths.is_empty() {
+            return;
+        }
+        match self.matching_openers().first() {
+            Some(rule) => self.run_opener(rule.clone(), paths).await,
+            None => self.show_notification("No opener matches this file".to_string()),
+        }
+    }
+
+    pub fn open_with_menu(&mut self) {
+        let paths = self.open_targets();
+        let rules = self.matching_openers();
+        if paths.is_empty() || rules.is_empty() {
+            self.show_notification("No opener matches this file".to_string());
+            return;
+        }
+        self.set_open_with(Some(OpenWith::new(paths, rules)));
+        self.set_popup(PopupType::OpenWith);
+    }
+
+    pub fn open_with_navigate(&mut self, down: bool) {
+        if let Some(menu) = self.open_with_mut() {
+            let len = menu.rules().len();
+            let current = menu.selection().selected().unwrap_or(0);
+            let next = if down {
+                (current + 1) % len
+            } else {
+                (current + len - 1) % len
+            };
+            menu.selection_mut().select(Some(next));
+        }
+    }
+
+    pub async fn open_with_selected(&mut self) {
+        let Some(menu) = self.take_open_with() else {
+            return;
+        };
+        self.set_popup(PopupType::None);
+        let index = menu.selection().selected().unwrap_or(0);
+        if let Some(rule) = menu.rules().get(index) {
+            self.run_opener(rule.clone(), menu.paths().to_vec()).await;
+        }
+    }
+
+    pub fn close_open_with(&mut self) {
+        self.take_open_with();
+        self.set_popup(PopupType::None);
+    }
+
+    pub async fn edit_selected(&mut self) {
+        let paths = self.open_targets();
+        if paths.is_empty() {
+            return;
+        }
+
+        let mut command = editor_command();
+        command.args(&paths).current_dir(self.current_path());
+        match self.run_in_terminal(&mut command).await {
+            Ok(status) if !status.success() => {
+                self.show_notification(format!("Editor exited with {status}"))
+            }
+            Ok(_) => {}
+            Err(e) => self.show_notification(format!("Failed to start editor: {e}")),
+        }
+        // The files may have changed, been renamed or deleted from inside the editor.
+        self.forget_dir_sizes();
+        self.reload_current_directory().await;
+    }
+
+    pub async fn run_opener(&mut self, rule: OpenerRule, paths: Vec<PathBuf>) {
+        let mut command = shell_command(&rule, &paths);
+        command.current_dir(self.current_path());
+
+        if rule.terminal() {
+            match self.run_in_terminal(&mut command).await {
+                Ok(status) if !status.success() => {
+                    self.show_notification(format!("{} exited with {status}", rule.name()))
+                }
+                Ok(_) => {}
+                Err(e) => self.show_notification(format!("{}: {e}", rule.name())),
+            }
+            // The program may have changed the files.
+            self.forget_dir_sizes();
+            self.reload_current_directory().await;
+        } else {
+            let spawned = command
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .process_group(0)
+                .spawn();
+            if let Err(e) = spawned {
+                self.show_notification(format!("{}: {e}", rule.name()));
+            }
+        }
+    }
+}
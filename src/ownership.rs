@@ -0,0 +1,246 @@
+use crate::modals::{FileManager, InteractionMode, OwnershipEditor, OwnershipField, PopupType};
+use crate::permissions::ChangeReport;
+use std::fs;
+use std::io;
+use std::os::unix::fs::{chown, MetadataExt};
+use uzers::{
+    all_groups, all_users, get_effective_uid, get_group_by_gid, get_group_by_name,
+    get_user_by_name, get_user_by_uid,
+};
+
+fn sorted(mut names: Vec<String>) -> Vec<String> {
+    names.sort();
+    names.dedup();
+    names
+}
+
+fn system_users() -> Vec<String> {
+    // SAFETY: getpwent keeps global state; nothing else enumerates users and the iterator is
+    // drained right here.
+    let users = unsafe { all_users() };
+    sorted(
+        users
+            .map(|user| user.name().to_string_lossy().to_string())
+            .collect(),
+    )
+}
+
+fn system_groups() -> Vec<String> {
+    // SAFETY: as above, with getgrent.
+    let groups = unsafe { all_groups() };
+    sorted(
+        groups
+            .map(|group| group.name().to_string_lossy().to_string())
+            .collect(),
+    )
+}
+
+// A name or a numeric id; `None` when the field is left empty.
+fn resolve_user(name: &str) -> Result<Option<u32>, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Ok(None);
+    }
+    if let Ok(uid) = name.parse() {
+        return Ok(Some(uid));
+    }
+    get_user_by_name(name)
+        .map(|user| Some(user.uid()))
+        .ok_or_else(|| format!("Unknown user: {name}"))
+}
+
+fn resolve_group(name: &str) -> Result<Option<u32>, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Ok(None);
+    }
+    if let Ok(gid) = name.parse() {
+        return Ok(Some(gid));
+    }
+    get_group_by_name(name)
+        .map(|group| Some(group.gid()))
+        .ok_or_else(|| format!("Unknown group: {name}"))
+}
+
+// Spells out which privilege is missing, since the OS only says "Operation not permitted".
+fn explain(e: io::Error, changes_owner: bool) -> String {
+    if e.kind() != io::ErrorKind::PermissionDenied || get_effective_uid() == 0 {
+        e.to_string()
+    } else if changes_owner {
+        format!("{e} (only root can give files away)")
+    } else {
+        format!("{e} (you must own it and belong to the group)")
+    }
+}
+
+fn common_prefix<'a>(names: &[&'a str]) -> &'a str {
+    let Some(first) = names.first() else {
+        return "";
+    };
+    let len = names.iter().skip(1).fold(first.len(), |len, name| {
+        first[..len]
+            .char_indices()
+            .zip(name.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(len.min(name.len()), |((i, _), _)| i)
+    });
+    &first[..len]
+}
+
+impl OwnershipField {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Owner => Self::Group,
+            Self::Group => Self::Recursive,
+            Self::Recursive => Self::Owner,
+        }
+    }
+
+    pub fn previous(self) -> Self {
+        match self {
+            Self::Owner => Self::Recursive,
+            Self::Group => Self::Owner,
+            Self::Recursive => Self::Group,
+        }
+    }
+}
+
+impl OwnershipEditor {
+    // Names starting with what has been typed in the focused field.
+    pub fn completions(&self) -> Vec<&str> {
+        let (input, names) = match self.focus() {
+            OwnershipField::Owner => (self.owner(), self.users()),
+            OwnershipField::Group => (self.group(), self.groups()),
+            OwnershipField::Recursive => return Vec::new(),
+        };
+        names
+            .iter()
+            .filter(|name| name.starts_with(input))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+impl FileManager {
+    pub fn open_ownership_editor(&mut self) {
+        let targets = self.selected_or_hovered_paths();
+        let Some(first) = targets.first() else {
+            return;
+        };
+        let metadata = match fs::metadata(first) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                self.show_notification(format!("{}: {e}", first.display()));
+                return;
+            }
+        };
+        let user = get_user_by_uid(metadata.uid()).map_or(metadata.uid().to_string(), |u| {
+            u.name().to_string_lossy().to_string()
+        });
+        let group = get_group_by_gid(metadata.gid()).map_or(metadata.gid().to_string(), |g| {
+            g.name().to_string_lossy().to_string()
+        });
+        let editor = OwnershipEditor::new(
+            targets,
+            format!("{user}:{group}"),
+            system_users(),
+            system_groups(),
+        );
+        self.set_ownership_editor(Some(editor));
+        self.set_popup(PopupType::Ownership);
+    }
+
+    pub fn ownership_focus(&mut self, forward: bool) {
+        if let Some(editor) = self.ownership_editor_mut() {
+            let focus = if forward {
+                editor.focus().next()
+            } else {
+                editor.focus().previous()
+            };
+            editor.set_focus(focus);
+        }
+    }
+
+    // Extends the focused name as far as the candidates agree, like shell completion.
+    pub fn ownership_complete(&mut self) {
+        let Some(editor) = self.ownership_editor_mut() else {
+            return;
+        };
+        let prefix = common_prefix(&editor.completions()).to_string();
+        if let Some(input) = editor.focused_input_mut() {
+            if prefix.len() > input.len() {
+                *input = prefix;
+            }
+        }
+    }
+
+    pub fn ownership_cycle_recursive(&mut self, forward: bool) {
+        if let Some(editor) = self.ownership_editor_mut() {
+            if editor.focus() == OwnershipField::Recursive {
+                let scope = if forward {
+                    editor.recursive().next()
+                } else {
+                    editor.recursive().previous()
+                };
+                editor.set_recursive(scope);
+            }
+        }
+    }
+
+    pub fn ownership_key(&mut self, c: char) {
+        let Some(editor) = self.ownership_editor_mut() else {
+            return;
+        };
+        match editor.focused_input_mut() {
+            Some(input) => input.push(c),
+            None if c == ' ' => self.ownership_cycle_recursive(true),
+            None => {}
+        }
+    }
+
+    pub fn ownership_backspace(&mut self) {
+        if let Some(editor) = self.ownership_editor_mut() {
+            if let Some(input) = editor.focused_input_mut() {
+                input.pop();
+            }
+        }
+    }
+
+    pub async fn apply_ownership_editor(&mut self) {
+        let Some(editor) = self.ownership_editor().cloned() else {
+            return;
+        };
+        let ids =
+            resolve_user(editor.owner()).and_then(|uid| Ok((uid, resolve_group(editor.group())?)));
+        let (uid, gid) = match ids {
+            Ok((None, None)) => {
+                self.show_notification("Enter an owner or a group".to_string());
+                return;
+            }
+            Ok(ids) => ids,
+            Err(e) => {
+                self.show_notification(e);
+                return;
+            }
+        };
+        let report = tokio::task::spawn_blocking(move || {
+            ChangeReport::apply(editor.targets(), editor.recursive(), &|path, _| {
+                chown(path, uid, gid).map_err(|e| explain(e, uid.is_some()))
+            })
+        })
+        .await;
+
+        self.cancel_ownership_editor();
+        self.set_mode(InteractionMode::Normal);
+        self.reload_current_directory().await;
+        match report {
+            Ok(report) => self.show_notification(report.summary("Changed ownership of")),
+            Err(e) => self.show_notification(e.to_string()),
+        }
+    }
+
+    pub fn cancel_ownership_editor(&mut self) {
+        self.set_ownership_editor(None);
+        self.set_popup(PopupType::None);
+    }
+}
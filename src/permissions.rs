@@ -0,0 +1,517 @@
+use crate::modals::{
+    FileManager, InteractionMode, PermissionEditor, PermissionField, PopupType, RecursiveScope,
+};
+use std::fs::{self, Metadata, Permissions};
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+// Bit shown at each grid cell: user, group and other rwx, then setuid, setgid and sticky.
+pub const GRID: [[u32; 3]; 4] = [
+    [0o400, 0o200, 0o100],
+    [0o040, 0o020, 0o010],
+    [0o004, 0o002, 0o001],
+    [0o4000, 0o2000, 0o1000],
+];
+
+// `ls`-style rwx string of the permission bits.
+pub fn format_mode(mode: u32) -> String {
+    let mut result = String::new();
+
+    // Each tuple is (bitmask, char to use if bit is set)
+    let flags = [
+        (0o400, 'r'),
+        (0o200, 'w'),
+        (0o100, 'x'), // user
+        (0o040, 'r'),
+        (0o020, 'w'),
+        (0o010, 'x'), // group
+        (0o004, 'r'),
+        (0o002, 'w'),
+        (0o001, 'x'), // others
+    ];
+
+    for (bit, ch) in flags {
+        if mode & bit != 0 {
+            result.push(ch);
+        } else {
+            result.push('-');
+        }
+    }
+
+    // setuid, setgid and sticky take the execute slot like `ls`: lowercase when
+    // execute is also set.
+    let specials = [(0o4000, 2, 's'), (0o2000, 5, 's'), (0o1000, 8, 't')];
+    for (bit, index, ch) in specials {
+        if mode & bit != 0 {
+            let executable = result.as_bytes()[index] == b'x';
+            let ch = if executable {
+                ch
+            } else {
+                ch.to_ascii_uppercase()
+            };
+            result.replace_range(index..=index, &ch.to_string());
+        }
+    }
+
+    result
+}
+
+// The inverse of `format_mode`.
+fn parse_listing(input: &str) -> Option<u32> {
+    let chars: Vec<char> = input.chars().collect();
+    if chars.len() != 9 {
+        return None;
+    }
+    let mut mode = 0;
+    for (index, ch) in chars.into_iter().enumerate() {
+        let bit = 0o400 >> index;
+        let special = match index {
+            2 => 0o4000,
+            5 => 0o2000,
+            8 => 0o1000,
+            _ => 0,
+        };
+        let letter = ['r', 'w', 'x'][index % 3];
+        let special_letter = if index == 8 { 't' } else { 's' };
+        match ch {
+            '-' => {}
+            ch if ch == letter => mode |= bit,
+            ch if special != 0 && ch == special_letter => mode |= bit | special,
+            ch if special != 0 && ch == special_letter.to_ascii_uppercase() => mode |= special,
+            _ => return None,
+        }
+    }
+    Some(mode)
+}
+
+// Spreads one rwx triplet to all three classes, for `g=u` style copies.
+fn copy_class(bits: u32) -> u32 {
+    bits * 0o111
+}
+
+// One `chmod` clause such as `u+x`, `go-w` or `a=rX`. Unlike chmod, no umask is applied
+// when the classes are left out.
+fn apply_clause(clause: &str, mode: u32, is_dir: bool) -> Result<u32, String> {
+    let invalid = || format!("Invalid mode: {clause}");
+    let who_end = clause.find(['+', '-', '=']).ok_or_else(invalid)?;
+    let (who, mut ops) = clause.split_at(who_end);
+
+    let mut mask = if who.is_empty() { 0o7777 } else { 0 };
+    for ch in who.chars() {
+        mask |= match ch {
+            'u' => 0o4700,
+            'g' => 0o2070,
+            'o' => 0o1007,
+            'a' => 0o7777,
+            _ => return Err(invalid()),
+        };
+    }
+
+    let mut mode = mode;
+    while let Some(op) = ops.chars().next() {
+        let rest = &ops[1..];
+        let end = rest.find(['+', '-', '=']).unwrap_or(rest.len());
+        let mut bits = 0;
+        for ch in rest[..end].chars() {
+            bits |= match ch {
+                'r' => 0o444,
+                'w' => 0o222,
+                'x' => 0o111,
+                // Execute only for directories and files someone can already execute.
+                'X' if is_dir || mode & 0o111 != 0 => 0o111,
+                'X' => 0,
+                's' => 0o6000,
+                't' => 0o1000,
+                'u' => copy_class((mode >> 6) & 0o7),
+                'g' => copy_class((mode >> 3) & 0o7),
+                'o' => copy_class(mode & 0o7),
+                _ => return Err(invalid()),
+            };
+        }
+        bits &= mask;
+        mode = match op {
+            '+' => mode | bits,
+            '-' => mode & !bits,
+            _ => (mode & !mask) | bits,
+        };
+        ops = &rest[end..];
+    }
+    Ok(mode)
+}
+
+// Octal (`755`), `ls`-style (`rwxr-xr-x`) or symbolic (`u+x,g-w`) input. Symbolic changes
+// are relative to `current`.
+pub fn parse_mode(input: &str, current: u32, is_dir: bool) -> Result<u32, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err("Empty mode".to_string());
+    }
+    if input.len() <= 4 && input.bytes().all(|b| (b'0'..=b'7').contains(&b)) {
+        return u32::from_str_radix(input, 8).map_err(|e| e.to_string());
+    }
+    if let Some(mode) = parse_listing(input) {
+        return Ok(mode);
+    }
+    input.split(',').try_fold(current & 0o7777, |mode, clause| {
+        apply_clause(clause, mode, is_dir)
+    })
+}
+
+impl RecursiveScope {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Off => Self::All,
+            Self::All => Self::Directories,
+            Self::Directories => Self::Files,
+            Self::Files => Self::Off,
+        }
+    }
+
+    pub fn previous(self) -> Self {
+        match self {
+            Self::Off => Self::Files,
+            Self::All => Self::Off,
+            Self::Directories => Self::All,
+            Self::Files => Self::Directories,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::All => "everything",
+            Self::Directories => "directories only",
+            Self::Files => "files only",
+        }
+    }
+
+    fn includes(self, is_dir: bool) -> bool {
+        match self {
+            Self::Off | Self::All => true,
+            Self::Directories => is_dir,
+            Self::Files => !is_dir,
+        }
+    }
+}
+
+impl PermissionField {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Grid => Self::Expression,
+            Self::Expression => Self::Recursive,
+            Self::Recursive => Self::Grid,
+        }
+    }
+
+    pub fn previous(self) -> Self {
+        match self {
+            Self::Grid => Self::Recursive,
+            Self::Expression => Self::Grid,
+            Self::Recursive => Self::Expression,
+        }
+    }
+}
+
+impl PermissionEditor {
+    // The mode the first target would end up with.
+    pub fn preview(&self) -> Result<u32, String> {
+        if self.expression().is_empty() {
+            Ok(self.mode())
+        } else {
+            parse_mode(self.expression(), self.base(), self.base_is_dir())
+        }
+    }
+
+    fn new_mode(&self, current: u32, is_dir: bool) -> Result<u32, String> {
+        if self.expression().is_empty() {
+            Ok(self.mode())
+        } else {
+            parse_mode(self.expression(), current, is_dir)
+        }
+    }
+}
+
+// Outcome of changing many paths, possibly recursively.
+pub struct ChangeReport {
+    changed: usize,
+    errors: Vec<String>,
+}
+
+type Change<'a> = &'a dyn Fn(&Path, &Metadata) -> Result<(), String>;
+
+impl ChangeReport {
+    // Targets that are links are followed, like chmod and chown do.
+    pub fn apply(targets: &[PathBuf], scope: RecursiveScope, change: Change) -> Self {
+        let mut report = Self {
+            changed: 0,
+            errors: Vec::new(),
+        };
+        for target in targets {
+            match fs::metadata(target) {
+                Ok(metadata) => report.walk(target, &metadata, scope, change),
+                Err(e) => report.errors.push(format!("{}: {e}", target.display())),
+            }
+        }
+        report
+    }
+
+    fn change(&mut self, path: &Path, metadata: &Metadata, change: Change) {
+        match change(path, metadata) {
+            Ok(()) => self.changed += 1,
+            Err(e) => self.errors.push(format!("{}: {e}", path.display())),
+        }
+    }
+
+    // Children are changed before their directory, so removing access from it does not lock
+    // the walk out; a directory that cannot be listed yet is changed first instead.
+    fn walk(&mut self, path: &Path, metadata: &Metadata, scope: RecursiveScope, change: Change) {
+        let wanted = scope.includes(metadata.is_dir());
+        let mut done = false;
+
+        if metadata.is_dir() && scope != RecursiveScope::Off {
+            let entries = match fs::read_dir(path) {
+                Err(_) if wanted => {
+                    self.change(path, metadata, change);
+                    done = true;
+                    fs::read_dir(path)
+                }
+                entries => entries,
+            };
+            match entries {
+                Ok(entries) => {
+                    let children: Vec<_> = entries.flatten().collect();
+                    for child in children {
+                        // Links are left alone rather than changing their target.
+                        match child.metadata() {
+                            Ok(child_metadata) if child_metadata.is_symlink() => {}
+                            Ok(child_metadata) => {
+                                self.walk(&child.path(), &child_metadata, scope, change)
+                            }
+                            Err(e) => self.errors.push(format!("{}: {e}", child.path().display())),
+                        }
+                    }
+                }
+                Err(e) => self.errors.push(format!("{}: {e}", path.display())),
+            }
+        }
+
+        if wanted && !done {
+            self.change(path, metadata, change);
+        }
+    }
+
+    // `done` describes a success, e.g. "Changed permissions of"; failures list the first few
+    // paths.
+    pub fn summary(&self, done: &str) -> String {
+        if self.errors.is_empty() {
+            return format!("{done} {} path(s)", self.changed);
+        }
+        let mut lines: Vec<String> = self.errors.iter().take(5).cloned().collect();
+        if self.errors.len() > lines.len() {
+            lines.push(format!("… and {} more", self.errors.len() - lines.len()));
+        }
+        format!(
+            "Changed {} path(s), {} failed:\n{}",
+            self.changed,
+            self.errors.len(),
+            lines.join("\n")
+        )
+    }
+}
+
+fn apply_permissions(editor: &PermissionEditor) -> ChangeReport {
+    ChangeReport::apply(editor.targets(), editor.recursive(), &|path, metadata| {
+        let mode = editor.new_mode(metadata.mode(), metadata.is_dir())?;
+        fs::set_permissions(path, Permissions::from_mode(mode)).map_err(|e| e.to_string())
+    })
+}
+
+impl FileManager {
+    pub fn open_permission_editor(&mut self) {
+        let targets = self.selected_or_hovered_paths();
+        let Some(first) = targets.first() else {
+            return;
+        };
+        match fs::metadata(first) {
+            Ok(metadata) => {
+                let editor =
+                    PermissionEditor::new(targets, metadata.mode() & 0o7777, metadata.is_dir());
+                self.set_permission_editor(Some(editor));
+                self.set_popup(PopupType::Permissions);
+            }
+            Err(e) => self.show_notification(format!("{}: {e}", first.display())),
+        }
+    }
+
+    pub fn permission_focus(&mut self, forward: bool) {
+        if let Some(editor) = self.permission_editor_mut() {
+            let focus = if forward {
+                editor.focus().next()
+            } else {
+                editor.focus().previous()
+            };
+            editor.set_focus(focus);
+        }
+    }
+
+    // Arrow keys move around the grid or cycle the recursive scope.
+    pub fn permission_move(&mut self, rows: isize, cols: isize) {
+        let Some(editor) = self.permission_editor_mut() else {
+            return;
+        };
+        match editor.focus() {
+            PermissionField::Grid => {
+                let (row, col) = editor.cursor();
+                let row = (row as isize + rows).rem_euclid(GRID.len() as isize) as usize;
+                let col = (col as isize + cols).rem_euclid(3) as usize;
+                editor.set_cursor((row, col));
+            }
+            PermissionField::Recursive if cols != 0 => {
+                let scope = if cols > 0 {
+                    editor.recursive().next()
+                } else {
+                    editor.recursive().previous()
+                };
+                editor.set_recursive(scope);
+            }
+            _ => {}
+        }
+    }
+
+    // Toggling a bit takes over whatever the expression produced.
+    pub fn permission_toggle(&mut self) {
+        let Some(editor) = self.permission_editor_mut() else {
+            return;
+        };
+        let Ok(mode) = editor.preview() else {
+            return;
+        };
+        let (row, col) = editor.cursor();
+        editor.set_mode(mode ^ GRID[row][col]);
+        editor.expression_mut().clear();
+    }
+
+    pub fn permission_key(&mut self, c: char) {
+        let Some(editor) = self.permission_editor_mut() else {
+            return;
+        };
+        match editor.focus() {
+            PermissionField::Expression => editor.expression_mut().push(c),
+            PermissionField::Grid if c == ' ' => self.permission_toggle(),
+            PermissionField::Grid | PermissionField::Recursive => match c {
+                'h' => self.permission_move(0, -1),
+                'l' => self.permission_move(0, 1),
+                'k' => self.permission_move(-1, 0),
+                'j' => self.permission_move(1, 0),
+                _ => {}
+            },
+        }
+    }
+
+    pub fn permission_backspace(&mut self) {
+        if let Some(editor) = self.permission_editor_mut() {
+            if editor.focus() == PermissionField::Expression {
+                editor.expression_mut().pop();
+            }
+        }
+    }
+
+    pub async fn apply_permission_editor(&mut self) {
+        let Some(editor) = self.permission_editor().cloned() else {
+            return;
+        };
+        if let Err(e) = editor.preview() {
+            self.show_notification(e);
+            return;
+        }
+        let report = tokio::task::spawn_blocking(move || apply_permissions(&editor)).await;
+
+        self.cancel_permission_editor();
+        self.set_mode(InteractionMode::Normal);
+        self.reload_current_directory().await;
+        match report {
+            Ok(report) => self.show_notification(report.summary("Changed permissions of")),
+            Err(e) => self.show_notification(e.to_string()),
+        }
+    }
+
+    pub fn cancel_permission_editor(&mut self) {
+        self.set_permission_editor(None);
+        self.set_popup(PopupType::None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_octal() {
+        assert_eq!(parse_mode("755", 0o600, false), Ok(0o755));
+        assert_eq!(parse_mode(" 0644 ", 0o777, false), Ok(0o644));
+        assert_eq!(parse_mode("4755", 0, false), Ok(0o4755));
+        assert_eq!(parse_mode("7777", 0, true), Ok(0o7777));
+    }
+
+    #[test]
+    fn applies_symbolic_clauses() {
+        assert_eq!(parse_mode("u+x,g-w", 0o664, false), Ok(0o744));
+        assert_eq!(parse_mode("+x", 0o644, false), Ok(0o755));
+        assert_eq!(parse_mode("o=", 0o777, false), Ok(0o770));
+        assert_eq!(parse_mode("u+x-w", 0o644, false), Ok(0o544));
+        // `X` only grants execute to directories and already executable files.
+        assert_eq!(parse_mode("a=rX", 0o640, false), Ok(0o444));
+        assert_eq!(parse_mode("a=rX", 0o740, false), Ok(0o555));
+        assert_eq!(parse_mode("a=rX", 0o700, true), Ok(0o555));
+        // Copies take the bits the class has before the clause.
+        assert_eq!(parse_mode("go=u", 0o750, false), Ok(0o777));
+        assert_eq!(parse_mode("o=g", 0o751, false), Ok(0o755));
+        // Special bits only reach the classes they belong to.
+        assert_eq!(parse_mode("u+s", 0o755, false), Ok(0o4755));
+        assert_eq!(parse_mode("g+s", 0o755, true), Ok(0o2755));
+        assert_eq!(parse_mode("o+s", 0o755, false), Ok(0o755));
+        assert_eq!(parse_mode("+t", 0o777, true), Ok(0o1777));
+        assert_eq!(parse_mode("a-s", 0o6755, false), Ok(0o755));
+        // The file type bits of `current` are dropped.
+        assert_eq!(parse_mode("u-w", 0o100644, false), Ok(0o444));
+    }
+
+    #[test]
+    fn parses_listings() {
+        assert_eq!(parse_mode("rwxr-xr-x", 0, false), Ok(0o755));
+        assert_eq!(parse_mode("rw-r-----", 0o777, false), Ok(0o640));
+        assert_eq!(parse_mode("rwsr-Sr-T", 0, false), Ok(0o7744));
+        assert_eq!(parse_mode("rwxrwsrwt", 0, true), Ok(0o3777));
+    }
+
+    #[test]
+    fn listing_round_trips_every_mode() {
+        for mode in 0..=0o7777 {
+            assert_eq!(parse_listing(&format_mode(mode)), Some(mode), "{mode:o}");
+        }
+        assert_eq!(format_mode(0o4755), "rwsr-xr-x");
+        assert_eq!(format_mode(0o2640), "rw-r-S---");
+        assert_eq!(format_mode(0o1777), "rwxrwxrwt");
+        assert_eq!(format_mode(0o1666), "rw-rw-rwT");
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        for input in [
+            "",
+            "  ",
+            "8",
+            "12345",
+            "u+z",
+            "q+x",
+            "ux",
+            "rwxrwxrwz",
+            "rwx",
+            "u+x,",
+        ] {
+            assert!(parse_mode(input, 0o644, false).is_err(), "{input:?}");
+        }
+        assert_eq!(parse_listing("rwtrwxrwx"), None);
+        assert_eq!(parse_listing("rwxrwxrws"), None);
+    }
+}
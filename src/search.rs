@@ -0,0 +1,237 @@
+use crate::modals::{FileManager, Filter, FilterKind, FsEntry, PopupType};
+use globset::{GlobBuilder, GlobMatcher};
+use regex::{Regex, RegexBuilder};
+use std::path::PathBuf;
+
+// Matching is smart-case: case-insensitive unless the pattern has an uppercase letter.
+#[derive(Debug, Clone)]
+pub enum NameMatcher {
+    Substring { needle: String, ignore_case: bool },
+    Glob(GlobMatcher),
+    Regex(Regex),
+}
+
+impl NameMatcher {
+    pub fn new(pattern: &str, kind: FilterKind) -> Result<Self, String> {
+        let ignore_case = !pattern.chars().any(char::is_uppercase);
+        match kind {
+            FilterKind::Substring => Ok(Self::Substring {
+                needle: if ignore_case {
+                    pattern.to_lowercase()
+                } else {
+                    pattern.to_string()
+                },
+                ignore_case,
+            }),
+            FilterKind::Glob => GlobBuilder::new(pattern)
+                .case_insensitive(ignore_case)
+                .literal_separator(true)
+                .build()
+                .map(|glob| Self::Glob(glob.compile_matcher()))
+                .map_err(|e| e.to_string()),
+            FilterKind::Regex => RegexBuilder::new(pattern)
+                .case_insensitive(ignore_case)
+                .build()
+                .map(Self::Regex)
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    pub fn is_match(&self, name: &str) -> bool {
+        match self {
+            Self::Substring {
+                needle,
+                ignore_case,
+            } => {
+                if *ignore_case {
+                    name.to_lowercase().contains(needle.as_str())
+                } else {
+                    name.contains(needle.as_str())
+                }
+            }
+            Self::Glob(glob) => glob.is_match(name),
+            Self::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+impl FilterKind {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Substring => Self::Glob,
+            Self::Glob => Self::Regex,
+            Self::Regex => Self::Substring,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Substring => "substring",
+            Self::Glob => "glob",
+            Self::Regex => "regex",
+        }
+    }
+}
+
+impl FileManager {
+    pub fn filtered_entries(&self, entries: &[FsEntry]) -> Vec<FsEntry> {
+        match self.filter() {
+            Some(filter) => entries
+                .iter()
+                .filter(|entry| filter.matcher().is_match(entry.display_name()))
+                .cloned()
+                .collect(),
+            None => entries.to_vec(),
+        }
+    }
+
+    // Re-applies the filter to the full listing, keeping the hovered entry and selection marks.
+    async fn refilter(&mut self) {
+        let hovered = self
+            .get_selected_index_entry_unmut()
+            .map(|entry| entry.entry_path().clone());
+        let selected: Vec<PathBuf> = self.get_selected_paths();
+
+        let mut entries = self.filtered_entries(self.all_entries());
+        for entry in &mut entries {
+            entry.is_selected = selected.contains(entry.entry_path());
+        }
+
+        let index = hovered
+            .and_then(|path| entries.iter().position(|e| e.entry_path() == &path))
+            .unwrap_or(0);
+        self.set_filtered_entries(entries);
+        self.selection_mut().select(Some(index));
+        self.refresh_preview().await;
+    }
+
+    pub fn start_search(&mut self) {
+        self.set_search_origin(self.selection().selected());
+        self.mut_input_buffer().clear();
+        self.set_popup(PopupType::Search);
+    }
+
+    pub async fn update_search(&mut self) {
+        let query = self.input_buffer().clone();
+        let origin = self.search_origin().unwrap_or(0);
+
+        let target = if query.is_empty() {
+            Some(origin)
+        } else {
+            self.find_match(&query, origin, true)
+        };
+
+        if let Some(index) = target {
+            self.selection_mut().select(Some(index));
+            self.refresh_preview().await;
+        }
+    }
+
+    pub fn confirm_search(&mut self) {
+        let query = self.input_buffer().clone();
+        if !query.is_empty() {
+            self.set_search_query(Some(query));
+        }
+        self.mut_input_buffer().clear();
+        self.set_search_origin(None);
+        self.set_popup(PopupType::None);
+    }
+
+    pub async fn cancel_search(&mut self) {
+        if let Some(origin) = self.search_origin() {
+            self.selection_mut().select(Some(origin));
+            self.refresh_preview().await;
+        }
+        self.mut_input_buffer().clear();
+        self.set_search_origin(None);
+        self.set_popup(PopupType::None);
+    }
+
+    pub async fn search_next(&mut self, forward: bool) {
+        let Some(query) = self.search_query().cloned() else {
+            return;
+        };
+        let len = self.entries().len();
+        if len == 0 {
+            return;
+        }
+
+        let current = self.selection().selected().unwrap_or(0);
+        let start = if forward {
+            (current + 1) % len
+        } else {
+            (current + len - 1) % len
+        };
+
+        match self.find_match(&query, start, forward) {
+            Some(index) => {
+                self.selection_mut().select(Some(index));
+                self.refresh_preview().await;
+            }
+            None => self.show_notification(format!("No matches for \"{query}\"")),
+        }
+    }
+
+    // Walks the visible entries from `start` (inclusive), wrapping around once.
+    fn find_match(&self, query: &str, start: usize, forward: bool) -> Option<usize> {
+        let matcher = NameMatcher::new(query, FilterKind::Substring).ok()?;
+        let len = self.entries().len();
+
+        (0..len)
+            .map(|offset| {
+                if forward {
+                    (start + offset) % len
+                } else {
+                    (start + len - offset) % len
+                }
+            })
+            .find(|&index| matcher.is_match(self.entries()[index].display_name()))
+    }
+
+    pub fn start_filter(&mut self) {
+        let (pattern, kind) = match self.filter() {
+            Some(filter) => (filter.pattern().to_string(), filter.kind()),
+            None => (String::new(), FilterKind::Substring),
+        };
+        *self.mut_input_buffer() = pattern;
+        self.set_filter_kind(kind);
+        self.set_popup(PopupType::Filter);
+    }
+
+    pub async fn update_filter(&mut self) {
+        let pattern = self.input_buffer().clone();
+        if pattern.is_empty() {
+            self.set_filter(None);
+        } else if let Ok(matcher) = NameMatcher::new(&pattern, self.filter_kind()) {
+            self.set_filter(Some(Filter::new(pattern, self.filter_kind(), matcher)));
+        } else {
+            // Keep the last valid filter while a pattern is only half typed.
+            return;
+        }
+        self.refilter().await;
+    }
+
+    pub async fn cycle_filter_kind(&mut self) {
+        self.set_filter_kind(self.filter_kind().next());
+        self.update_filter().await;
+    }
+
+    pub fn confirm_filter(&mut self) {
+        let pattern = self.input_buffer().clone();
+        if let Err(e) = NameMatcher::new(&pattern, self.filter_kind()) {
+            self.show_notification(format!(
+                "Invalid {} filter: {e}",
+                self.filter_kind().label()
+            ));
+        }
+        self.mut_input_buffer().clear();
+        self.set_popup(PopupType::None);
+    }
+
+    pub async fn clear_filter(&mut self) {
+        self.set_filter(None);
+        self.mut_input_buffer().clear();
+        self.set_popup(PopupType::None);
+        self.refilter().await;
+    }
+}
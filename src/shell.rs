@@ -0,0 +1,285 @@
+use crate::modals::{FileManager, PopupType, ShellEvent, ShellJob, ShellMode};
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+use tokio::sync::mpsc::UnboundedSender;
+
+const MAX_LOG_LINES: usize = 5000;
+// Exported to subshells (like ranger's RANGER_LEVEL) so prompts can show the nesting depth.
+const LEVEL_VAR: &str = "ARBOR_LEVEL";
+
+impl ShellMode {
+    pub fn toggle(self) -> Self {
+        match self {
+            Self::Foreground => Self::Background,
+            Self::Background => Self::Foreground,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Foreground => "foreground",
+            Self::Background => "background",
+        }
+    }
+}
+
+// Works on the raw bytes, so paths that aren't UTF-8 reach the shell unchanged.
+pub fn shell_quote(text: &OsStr) -> OsString {
+    let mut quoted = vec![b'\''];
+    for &byte in text.as_bytes() {
+        if byte == b'\'' {
+            quoted.extend_from_slice(br"'\''");
+        } else {
+            quoted.push(byte);
+        }
+    }
+    quoted.push(b'\'');
+    OsString::from_vec(quoted)
+}
+
+// Expands `%f` (hovered file), `%s` (selected paths), `%d` (directory) and `%%`.
+// Every path is quoted, so the placeholders must not be quoted again.
+pub fn expand_placeholders(
+    command: &str,
+    hovered: Option<&Path>,
+    selected: &[PathBuf],
+    dir: &Path,
+) -> OsString {
+    let quote = |path: &Path| shell_quote(path.as_os_str());
+    let mut expanded = OsString::with_capacity(command.len());
+    let mut chars = command.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            expanded.push(c.encode_utf8(&mut [0; 4]));
+            continue;
+        }
+        match chars.peek() {
+            Some('f') => {
+                chars.next();
+                if let Some(path) = hovered {
+                    expanded.push(quote(path));
+                }
+            }
+            Some('s') => {
+                chars.next();
+                let paths: Vec<OsString> = selected.iter().map(|path| quote(path)).collect();
+                expanded.push(paths.join(OsStr::new(" ")));
+            }
+            Some('d') => {
+                chars.next();
+                expanded.push(quote(dir));
+            }
+            Some('%') => {
+                chars.next();
+                expanded.push("%");
+            }
+            _ => expanded.push("%"),
+        }
+    }
+
+    expanded
+}
+
+async fn forward_lines(
+    reader: impl AsyncRead + Unpin,
+    job: usize,
+    tx: UnboundedSender<ShellEvent>,
+) {
+    // Read as bytes: stopping at a line that isn't UTF-8 would leave the command writing
+    // into a closed pipe.
+    let mut reader = BufReader::new(reader);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line).await {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        let text = String::from_utf8_lossy(&line);
+        let text = text.trim_end_matches(['\n', '\r']).to_string();
+        if tx.send(ShellEvent::Output(job, text)).is_err() {
+            break;
+        }
+    }
+}
+
+impl FileManager {
+    pub fn start_shell_prompt(&mut self) {
+        self.mut_input_buffer().clear();
+        self.set_popup(PopupType::Shell);
+    }
+
+    pub fn toggle_shell_mode(&mut self) {
+        self.set_shell_mode(self.shell_mode().toggle());
+    }
+
+    pub async fn run_shell_command(&mut self) {
+        let input = self.input_buffer().clone();
+        if input.trim().is_empty() {
+            return;
+        }
+        self.mut_input_buffer().clear();
+        self.set_popup(PopupType::None);
+
+        let hovered = self
+            .get_selected_index_entry_unmut()
+            .map(|entry| entry.entry_path().clone());
+        let mut selected = self.get_selected_paths();
+        if selected.is_empty() {
+            selected.extend(hovered.clone());
+        }
+        let script =
+            expand_placeholders(&input, hovered.as_deref(), &selected, self.current_path());
+
+        match self.shell_mode() {
+            ShellMode::Foreground => self.run_shell_foreground(script).await,
+            ShellMode::Background => self.run_shell_background(input, script),
+        }
+    }
+
+    async fn run_shell_foreground(&mut self, mut script: OsString) {
+        // Keep the output on screen until it has been read.
+        script.push(
+            "\nstatus=$?\nprintf '\\n[exit %s] Press Enter to return to Arbor' \"$status\"\nread -r _",
+        );
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(script)
+            .current_dir(self.current_path());
+
+        if let Err(e) = self.run_in_terminal(&mut command).await {
+            self.show_notification(format!("Failed to run command: {e}"));
+        }
+        self.forget_dir_sizes();
+        self.reload_current_directory().await;
+    }
+
+    fn run_shell_background(&mut self, label: String, script: OsString) {
+        let job = self.shell_jobs().len();
+        let spawned = Command::new("sh")
+            .arg("-c")
+            .arg(script)
+            .current_dir(self.current_path())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .process_group(0)
+            .spawn();
+
+        let mut child = match spawned {
+            Ok(child) => child,
+            Err(e) => {
+                self.show_notification(format!("Failed to run command: {e}"));
+                return;
+            }
+        };
+
+        self.shell_jobs_mut().push(ShellJob::new(label.clone()));
+        let tx = self.shell_events_tx().clone();
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+
+        tokio::spawn(async move {
+            let stdout = stdout.map(|out| tokio::spawn(forward_lines(out, job, tx.clone())));
+            let stderr = stderr.map(|err| tokio::spawn(forward_lines(err, job, tx.clone())));
+            let status = child.wait().await;
+            for reader in [stdout, stderr].into_iter().flatten() {
+                let _ = reader.await;
+            }
+            let code = status.ok().and_then(|status| status.code());
+            let _ = tx.send(ShellEvent::Exited(job, code));
+        });
+
+        self.show_notification(format!("Running in background: {label} (L for the log)"));
+    }
+
+    // Applies output from background commands, along with whatever else has arrived since;
+    // the listing is reloaded when one finishes.
+    pub async fn handle_shell_event(&mut self, event: ShellEvent) {
+        let mut finished = false;
+        let mut next = Some(event);
+
+        while let Some(event) = next {
+            match event {
+                ShellEvent::Output(index, line) => {
+                    if let Some(job) = self.shell_jobs_mut().get_mut(index) {
+                        // Keep the most recent output.
+                        if job.output().len() >= MAX_LOG_LINES {
+                            job.output_mut().pop_front();
+                        }
+                        job.output_mut().push_back(line);
+                    }
+                }
+                ShellEvent::Exited(index, code) => {
+                    if let Some(job) = self.shell_jobs_mut().get_mut(index) {
+                        job.finish(code);
+                        let status = code.map_or("a signal".to_string(), |c| format!("code {c}"));
+                        let message = format!("`{}` exited with {status}", job.command());
+                        self.show_notification(message);
+                    }
+                    finished = true;
+                }
+            }
+            next = self.shell_events_rx_mut().try_recv().ok();
+        }
+
+        if finished {
+            self.forget_dir_sizes();
+            self.reload_current_directory().await;
+        }
+    }
+
+    pub fn open_shell_log(&mut self) {
+        let len = self.shell_jobs().len();
+        if len == 0 {
+            self.show_notification("No background commands yet".to_string());
+            return;
+        }
+        self.shell_log_selection_mut().select(Some(len - 1));
+        self.set_popup(PopupType::ShellLog);
+    }
+
+    pub fn shell_log_navigate(&mut self, down: bool) {
+        let len = self.shell_jobs().len();
+        if len == 0 {
+            return;
+        }
+        let current = self.shell_log_selection().selected().unwrap_or(0);
+        let next = if down {
+            (current + 1) % len
+        } else {
+            (current + len - 1) % len
+        };
+        self.shell_log_selection_mut().select(Some(next));
+    }
+
+    pub async fn spawn_subshell(&mut self) {
+        let shell = env::var("SHELL")
+            .ok()
+            .filter(|shell| !shell.trim().is_empty())
+            .unwrap_or_else(|| "sh".to_string());
+        let level = env::var(LEVEL_VAR)
+            .ok()
+            .and_then(|level| level.parse::<u32>().ok())
+            .unwrap_or(0)
+            + 1;
+
+        let mut command = Command::new(&shell);
+        command
+            .current_dir(self.current_path())
+            .env(LEVEL_VAR, level.to_string());
+
+        if let Err(e) = self.run_in_terminal(&mut command).await {
+            self.show_notification(format!("Failed to start {shell}: {e}"));
+        }
+        self.forget_dir_sizes();
+        self.reload_current_directory().await;
+    }
+}
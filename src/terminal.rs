@@ -0,0 +1,99 @@
+use crate::crash;
+use crate::modals::FileManager;
+use crossterm::{
+    cursor,
+    event::{DisableBracketedPaste, EnableBracketedPaste},
+    execute,
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{backend::CrosstermBackend, Terminal};
+use std::env;
+use std::io::{self, stderr, stdout, IsTerminal, Write};
+use std::os::fd::AsFd;
+use std::panic;
+use std::process::{ExitStatus, Stdio};
+use tokio::process::Command;
+
+pub type Tui = Terminal<CrosstermBackend<Box<dyn Write + Send>>>;
+
+// The interface goes to stderr when stdout is captured, e.g. `$(arbor --chooser-file -)`.
+fn screen() -> Box<dyn Write + Send> {
+    if stdout().is_terminal() {
+        Box::new(stdout())
+    } else {
+        Box::new(stderr())
+    }
+}
+
+pub fn init() -> io::Result<Tui> {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let _ = suspend();
+        hook(info);
+        match crash::write_report(info) {
+            Ok(file) => eprintln!("arbor: crash report written to {}", file.display()),
+            Err(e) => eprintln!("arbor: could not write a crash report: {e}"),
+        }
+        // A panic on a tokio worker would otherwise leave the UI running on a restored
+        // terminal, so any panic ends the process, with the exit code of a panicked main.
+        std::process::exit(101);
+    }));
+    // Raw mode may already be on when entering the alternate screen fails.
+    if let Err(e) = resume() {
+        let _ = suspend();
+        return Err(e);
+    }
+    Terminal::new(CrosstermBackend::new(screen()))
+}
+
+pub fn suspend() -> io::Result<()> {
+    terminal::disable_raw_mode()?;
+    execute!(
+        screen(),
+        DisableBracketedPaste,
+        LeaveAlternateScreen,
+        cursor::Show
+    )
+}
+
+pub fn resume() -> io::Result<()> {
+    terminal::enable_raw_mode()?;
+    execute!(
+        screen(),
+        EnterAlternateScreen,
+        EnableBracketedPaste,
+        cursor::Hide
+    )
+}
+
+// `$VISUAL`, then `$EDITOR`, then `vi`. Arguments in the variable (`code -w`) are kept.
+pub fn editor_command() -> Command {
+    let editor = env::var("VISUAL")
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+        .or_else(|| env::var("EDITOR").ok())
+        .filter(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+
+    let mut parts = editor.split_whitespace();
+    let mut command = Command::new(parts.next().unwrap_or("vi"));
+    command.args(parts);
+    command
+}
+
+impl FileManager {
+    // Hands the terminal to `command` until it exits, then takes it back.
+    pub async fn run_in_terminal(&mut self, command: &mut Command) -> io::Result<ExitStatus> {
+        // Keep programs from writing into a captured stdout.
+        if !stdout().is_terminal() {
+            command.stdout(Stdio::from(stderr().as_fd().try_clone_to_owned()?));
+        }
+        // Dropping the input stream stops it from reading keys meant for the program.
+        self.close_input();
+        suspend()?;
+        let status = command.status().await;
+        resume()?;
+        self.request_full_redraw();
+        status
+    }
+}
@@ -1,6 +1,9 @@
-use crate::utils::{
-    bottom_right_area, convert_to_listitems, format_size, mode_to_string, popup_area,
+use crate::bulk_rename::RenameStatus;
+use crate::modals::{
+    AnalyzerAction, DuNode, OwnershipField, PatternField, PermissionField, SymlinkField,
 };
+use crate::permissions::{format_mode, GRID};
+use crate::utils::{bottom_right_area, convert_to_listitems, format_size, git_span, popup_area};
 use crate::{
     Action, FileContent, FileManager, FsEntryType, InteractionMode, PopupType, PreviewContent,
 };
@@ -29,25 +32,37 @@ impl FileManager {
                     match clipboard_action {
                         Action::Move => ("▌", Style::default().fg(Color::Red)),
                         Action::Copy => ("▌", Style::default().fg(Color::Green)),
+                        Action::Symlink | Action::RelativeSymlink | Action::Hardlink => {
+                            ("▌", Style::default().fg(Color::Cyan))
+                        }
                         Action::None => ("▌", Style::default().fg(Color::Yellow)),
                     }
                 } else {
                     (" ", Style::default())
                 };
 
-                let icon = match entry.entry_type() {
-                    FsEntryType::Directory => "📁",
-                    FsEntryType::File => "📄",
-                    FsEntryType::Symlink => "🔗",
-                };
+                let icon = entry.entry_type().icon();
 
                 let is_cursor_row = cursor_index == Some(index);
+                let git_marker = git_span(self.git(), entry);
+                let size_label = match self.dir_size_label(entry.entry_path()) {
+                    Some(size) => {
+                        Span::styled(format!("  {size}"), Style::default().fg(Color::Gray))
+                    }
+                    None => Span::raw(""),
+                };
+                let owner_label = match entry.owner() {
+                    Some(owner) => {
+                        Span::styled(format!(" @{owner}"), Style::default().fg(Color::LightRed))
+                    }
+                    None => Span::raw(""),
+                };
 
                 let text = Line::from(vec![
                     Span::styled(bar, bar_style),
                     Span::raw(" "),
                     Span::styled(
-                        format!("{} {}", icon, entry.name()),
+                        format!("{} {}", icon, entry.display_name()),
                         if is_cursor_row {
                             Style::default()
                                 .bg(Color::Blue)
@@ -57,13 +72,16 @@ impl FileManager {
                             Style::default()
                         },
                     ),
+                    git_marker,
+                    owner_label,
+                    size_label,
                 ]);
 
                 ListItem::new(text)
             })
             .collect();
 
-        let list_parent_items: Vec<ListItem> = convert_to_listitems(parent_files);
+        let list_parent_items: Vec<ListItem> = convert_to_listitems(parent_files, self.git());
 
         let current_directory = Paragraph::new(current_path);
         let block = Block::bordered().border_type(Rounded).borders(Borders::ALL);
@@ -95,7 +113,7 @@ impl FileManager {
 
         match &self.preview_mut() {
             PreviewContent::Directory(sub_files) => {
-                let list_sub_items: Vec<ListItem> = convert_to_listitems(sub_files);
+                let list_sub_items: Vec<ListItem> = convert_to_listitems(sub_files, self.git());
 
                 let preview_directory_list = List::new(list_sub_items);
                 let inner_area = block.inner(layout[2]);
@@ -110,8 +128,28 @@ impl FileManager {
                 }
             }
             PreviewContent::File(FileContent::Text(data)) => {
-                let preview_file_content_txt =
-                    Paragraph::new(String::from(data)).wrap(Wrap { trim: true });
+                let preview_file_content_txt = match self.preview_line() {
+                    // Jumped here from a content search: unwrapped so the scroll offset is exact.
+                    Some(target) => {
+                        let lines: Vec<Line> = data
+                            .lines()
+                            .enumerate()
+                            .map(|(index, line)| {
+                                if index + 1 == target {
+                                    Line::styled(
+                                        line.to_string(),
+                                        Style::default().bg(Color::Yellow).fg(Color::Black),
+                                    )
+                                } else {
+                                    Line::raw(line.to_string())
+                                }
+                            })
+                            .collect();
+                        let offset = u16::try_from(target.saturating_sub(3)).unwrap_or(u16::MAX);
+                        Paragraph::new(lines).scroll((offset, 0))
+                    }
+                    None => Paragraph::new(String::from(data)).wrap(Wrap { trim: true }),
+                };
 
                 let inner_area = block.inner(layout[2]);
 
@@ -119,6 +157,33 @@ impl FileManager {
                 f.render_widget(&block, layout[2]);
                 f.render_widget(preview_file_content_txt, inner_area);
             }
+            PreviewContent::File(FileContent::Diff(data)) => {
+                let lines: Vec<Line> = data
+                    .lines()
+                    .map(|line| {
+                        let style = if line.starts_with("@@") {
+                            Style::default().fg(Color::Cyan)
+                        } else if line.starts_with('+') && !line.starts_with("+++") {
+                            Style::default().fg(Color::Green)
+                        } else if line.starts_with('-') && !line.starts_with("---") {
+                            Style::default().fg(Color::Red)
+                        } else {
+                            Style::default()
+                        };
+                        Line::styled(line.to_string(), style)
+                    })
+                    .collect();
+                let preview_diff = Paragraph::new(lines);
+                let inner_area = block.inner(layout[2]);
+
+                f.render_widget(Clear, layout[2]);
+                f.render_widget(&block, layout[2]);
+                f.render_widget(preview_diff, inner_area);
+            }
+            PreviewContent::Unavailable(reason) => {
+                f.render_widget(Clear, layout[2]);
+                f.render_widget(unavailable(reason, block.clone()), layout[2]);
+            }
             PreviewContent::File(FileContent::Binary(data)) => {
                 let preview_file_content_binary =
                     Paragraph::new(data.to_string()).wrap(Wrap { trim: true });
@@ -131,7 +196,10 @@ impl FileManager {
         }
 
         f.render_widget(current_directory, main_layout[0]);
-        f.render_widget(list_parent_files, layout[0]);
+        match self.parent_view().error() {
+            Some(reason) => f.render_widget(unavailable(reason, block.clone()), layout[0]),
+            None => f.render_widget(list_parent_files, layout[0]),
+        }
 
         if entry_lists.is_empty() {
             f.render_widget(&empty_lists, layout[1]);
@@ -139,6 +207,10 @@ impl FileManager {
             f.render_stateful_widget(entry_lists, layout[1], self.selection_mut());
         }
 
+        if let PopupType::Analyzer = &self.popup() {
+            self.render_analyzer(f, main_layout[0], &layout);
+        }
+
         if let PopupType::Confirm = &self.popup() {
             let mut confirm_file_list = Paragraph::new("").wrap(Wrap { trim: false });
 
@@ -146,8 +218,7 @@ impl FileManager {
                 InteractionMode::Normal => {
                     if let Some(index) = self.selection().selected() {
                         if let Some(file) = self.entries().get(index) {
-                            let name = file.name();
-                            let path = self.current_path().join(name).to_string_lossy().to_string();
+                            let path = file.entry_path().to_string_lossy().to_string();
 
                             confirm_file_list = Paragraph::new(path)
                                 .alignment(Alignment::Left)
@@ -250,6 +321,535 @@ impl FileManager {
             f.render_widget(input_paragraph, area);
         }
 
+        if let PopupType::Search = &self.popup() {
+            let input = self.input_buffer();
+            let input_paragraph = Paragraph::new(format!("/{input}")).block(
+                Block::bordered()
+                    .border_type(Rounded)
+                    .title("Search")
+                    .blue(),
+            );
+
+            let area = popup_area(f.area(), 30, 10);
+
+            f.render_widget(Clear, area);
+            f.render_widget(input_paragraph, area);
+        }
+
+        if let PopupType::Filter = &self.popup() {
+            let title = format!("Filter ({}) - Tab to switch", self.filter_kind().label());
+            let input = self.input_buffer();
+            let input_paragraph = Paragraph::new(input.clone())
+                .block(Block::bordered().border_type(Rounded).title(title).blue());
+
+            let area = popup_area(f.area(), 30, 10);
+
+            f.render_widget(Clear, area);
+            f.render_widget(input_paragraph, area);
+        }
+
+        if let PopupType::Grep = &self.popup() {
+            let input = self.input_buffer();
+            let input_paragraph = Paragraph::new(input.clone()).block(
+                Block::bordered()
+                    .border_type(Rounded)
+                    .title("Search file contents")
+                    .blue(),
+            );
+
+            let area = popup_area(f.area(), 30, 10);
+
+            f.render_widget(Clear, area);
+            f.render_widget(input_paragraph, area);
+        }
+
+        if let PopupType::GrepResults = self.popup().clone() {
+            if let Some(grep) = self.grep() {
+                let status = if grep.is_running() {
+                    "searching..."
+                } else {
+                    "done"
+                };
+                let title = format!(
+                    "\"{}\": {} matches in {} files scanned ({status})",
+                    grep.query(),
+                    grep.results().len(),
+                    grep.files_scanned()
+                );
+
+                let items: Vec<ListItem> = grep
+                    .results()
+                    .iter()
+                    .map(|found| {
+                        let path = found
+                            .path()
+                            .strip_prefix(grep.root())
+                            .unwrap_or(found.path());
+                        ListItem::new(Line::from(vec![
+                            Span::styled(
+                                format!("{}:{}", path.to_string_lossy(), found.line()),
+                                Style::default().fg(Color::LightCyan),
+                            ),
+                            Span::raw("  "),
+                            Span::raw(found.snippet().to_string()),
+                        ]))
+                    })
+                    .collect();
+
+                let results_list = List::new(items)
+                    .highlight_style(Style::default().bg(Color::Blue).fg(Color::Black))
+                    .block(Block::bordered().border_type(Rounded).title(title).blue());
+                let mut selection = grep.selection().clone();
+
+                let area = popup_area(f.area(), 80, 70);
+
+                f.render_widget(Clear, area);
+                f.render_stateful_widget(results_list, area, &mut selection);
+            }
+        }
+
+        if let PopupType::BulkRename = &self.popup() {
+            let plan = self.pending_renames();
+            let lines: Vec<Line> = plan
+                .iter()
+                .map(|(src, dst)| {
+                    let old = src.file_name().unwrap_or_default().to_string_lossy();
+                    let new = dst.file_name().unwrap_or_default().to_string_lossy();
+                    Line::from(vec![
+                        Span::raw(old.to_string()),
+                        Span::styled(" → ", Style::default().fg(Color::LightBlue)),
+                        Span::styled(new.to_string(), Style::default().fg(Color::Green)),
+                    ])
+                })
+                .collect();
+
+            let title = format!("Rename {} entries? Yes(Y) No(N)", plan.len());
+            let summary = Paragraph::new(lines)
+                .wrap(Wrap { trim: false })
+                .block(Block::bordered().border_type(Rounded).title(title).blue());
+
+            let area = popup_area(f.area(), 60, 60);
+
+            f.render_widget(Clear, area);
+            f.render_widget(summary, area);
+        }
+
+        if let PopupType::Info = &self.popup() {
+            let lines: Vec<Line> = self
+                .info()
+                .iter()
+                .map(|(label, value)| {
+                    Line::from(vec![
+                        Span::styled(
+                            format!("{label:<10} "),
+                            Style::default()
+                                .fg(Color::LightBlue)
+                                .add_modifier(Modifier::BOLD),
+                        ),
+                        Span::raw(value.clone()),
+                    ])
+                })
+                .collect();
+            let details = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+                Block::bordered()
+                    .border_type(Rounded)
+                    .title("Info - Esc to close")
+                    .blue(),
+            );
+
+            let area = popup_area(f.area(), 60, 60);
+
+            f.render_widget(Clear, area);
+            f.render_widget(details, area);
+        }
+
+        if let PopupType::GitRestore = &self.popup() {
+            let lines: Vec<Line> = self
+                .git_restore()
+                .iter()
+                .map(|path| Line::from(path.to_string_lossy().to_string()))
+                .collect();
+            let title = format!(
+                "Discard all changes to {} path(s)? Yes(Y) No(N)",
+                self.git_restore().len()
+            );
+            let summary = Paragraph::new(lines)
+                .wrap(Wrap { trim: false })
+                .block(Block::bordered().border_type(Rounded).title(title).red());
+
+            let area = popup_area(f.area(), 60, 40);
+
+            f.render_widget(Clear, area);
+            f.render_widget(summary, area);
+        }
+
+        if let Some(rename) = self.pattern_rename() {
+            let area = popup_area(f.area(), 70, 70);
+            let block = Block::bordered()
+                .border_type(Rounded)
+                .title("Pattern rename - Tab: next field, ←/→: case, Enter: apply")
+                .blue();
+            let inner_area = block.inner(area);
+            let sections =
+                Layout::vertical([Constraint::Length(5), Constraint::Min(1)]).split(inner_area);
+
+            let field = |label: &str, value: &str, focused: bool| {
+                let style = if focused {
+                    Style::default().fg(Color::Black).bg(Color::Blue)
+                } else {
+                    Style::default()
+                };
+                Line::from(vec![
+                    Span::styled(format!("{label:<10}"), Style::default().fg(Color::Gray)),
+                    Span::styled(value.to_string(), style),
+                ])
+            };
+            let focus = rename.focus();
+            let fields = Paragraph::new(vec![
+                field("Find", rename.find(), focus == PatternField::Find),
+                field("Replace", rename.replace(), focus == PatternField::Replace),
+                field("Case", rename.case().label(), focus == PatternField::Case),
+                field(
+                    "Extension",
+                    rename.extension(),
+                    focus == PatternField::Extension,
+                ),
+                Line::styled(
+                    "Replace supports $1 / ${name} captures and {n} or {n:3} numbering",
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]);
+
+            let preview: Vec<Line> = match rename.preview() {
+                Ok(rows) => rows
+                    .iter()
+                    .map(|row| {
+                        let old = row.source.file_name().unwrap_or_default().to_string_lossy();
+                        let (color, note) = match row.status {
+                            RenameStatus::Unchanged => (Color::DarkGray, ""),
+                            RenameStatus::Renamed => (Color::Green, ""),
+                            RenameStatus::Invalid => (Color::Red, "  (invalid name)"),
+                            RenameStatus::Collision => (Color::Red, "  (collision)"),
+                            RenameStatus::NotUtf8 => (Color::Red, "  (name is not UTF-8)"),
+                        };
+                        Line::from(vec![
+                            Span::raw(old.to_string()),
+                            Span::styled(" → ", Style::default().fg(Color::LightBlue)),
+                            Span::styled(
+                                format!("{}{note}", row.new_name),
+                                Style::default().fg(color),
+                            ),
+                        ])
+                    })
+                    .collect(),
+                Err(e) => vec![Line::styled(e.as_str(), Style::default().fg(Color::Red))],
+            };
+
+            f.render_widget(Clear, area);
+            f.render_widget(block, area);
+            f.render_widget(fields, sections[0]);
+            f.render_widget(
+                Paragraph::new(preview)
+                    .wrap(Wrap { trim: false })
+                    .block(Block::default().borders(Borders::TOP)),
+                sections[1],
+            );
+        }
+
+        if let Some(editor) = self.permission_editor() {
+            let area = popup_area(f.area(), 60, 40);
+            let title = format!(
+                "Permissions of {} path(s) - Tab: field, Space: toggle, Enter: apply",
+                editor.targets().len()
+            );
+            let block = Block::bordered().border_type(Rounded).title(title).blue();
+            let inner_area = block.inner(area);
+
+            let preview = editor.preview();
+            let mode = preview.clone().unwrap_or(editor.mode());
+            let focus = editor.focus();
+            let focused = Style::default().fg(Color::Black).bg(Color::Blue);
+            let label =
+                |text: &str| Span::styled(format!("{text:<10}"), Style::default().fg(Color::Gray));
+
+            let mut lines = vec![Line::from(vec![
+                label(""),
+                Span::styled(" r   w   x", Style::default().fg(Color::Gray)),
+            ])];
+            let rows = ["User", "Group", "Other", "Special"];
+            let specials = ["suid", "sgid", "sticky"];
+            for (row, bits) in GRID.iter().enumerate() {
+                let mut spans = vec![label(rows[row])];
+                for (col, bit) in bits.iter().enumerate() {
+                    let cell = if mode & bit != 0 { "[x]" } else { "[ ]" };
+                    let style = if focus == PermissionField::Grid && editor.cursor() == (row, col) {
+                        focused
+                    } else {
+                        Style::default()
+                    };
+                    spans.push(Span::styled(cell, style));
+                    spans.push(Span::raw(" "));
+                }
+                if row == 3 {
+                    spans.push(Span::styled(
+                        specials.join(" / "),
+                        Style::default().fg(Color::DarkGray),
+                    ));
+                }
+                lines.push(Line::from(spans));
+            }
+
+            let field_style = |field: PermissionField| {
+                if focus == field {
+                    focused
+                } else {
+                    Style::default()
+                }
+            };
+            lines.push(Line::from(""));
+            lines.push(Line::from(vec![
+                label("Mode"),
+                Span::styled(
+                    format!("{} ({mode:04o})", format_mode(mode)),
+                    Style::default().fg(Color::Green),
+                ),
+            ]));
+            lines.push(Line::from(vec![
+                label("Set to"),
+                Span::styled(
+                    editor.expression().to_string(),
+                    field_style(PermissionField::Expression),
+                ),
+            ]));
+            lines.push(Line::from(vec![
+                label("Recursive"),
+                Span::styled(
+                    editor.recursive().label(),
+                    field_style(PermissionField::Recursive),
+                ),
+            ]));
+            lines.push(Line::from(""));
+            lines.push(match preview {
+                Err(e) => Line::styled(e, Style::default().fg(Color::Red)),
+                Ok(_) => Line::styled(
+                    "Set to: 755, rwxr-xr-x or u+x,g-w per target; empty applies the grid",
+                    Style::default().fg(Color::DarkGray),
+                ),
+            });
+
+            f.render_widget(Clear, area);
+            f.render_widget(block, area);
+            f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), inner_area);
+        }
+
+        if let Some(editor) = self.ownership_editor() {
+            let area = popup_area(f.area(), 60, 40);
+            let title = format!(
+                "Ownership of {} path(s) - ↑/↓: field, Tab: complete, Enter: apply",
+                editor.targets().len()
+            );
+            let block = Block::bordered().border_type(Rounded).title(title).blue();
+            let inner_area = block.inner(area);
+            let sections =
+                Layout::vertical([Constraint::Length(5), Constraint::Min(1)]).split(inner_area);
+
+            let focus = editor.focus();
+            let field = |label: &str, value: &str, focused: bool| {
+                let style = if focused {
+                    Style::default().fg(Color::Black).bg(Color::Blue)
+                } else {
+                    Style::default()
+                };
+                Line::from(vec![
+                    Span::styled(format!("{label:<10}"), Style::default().fg(Color::Gray)),
+                    Span::styled(value.to_string(), style),
+                ])
+            };
+            let fields = Paragraph::new(vec![
+                field("Owner", editor.owner(), focus == OwnershipField::Owner),
+                field("Group", editor.group(), focus == OwnershipField::Group),
+                field(
+                    "Recursive",
+                    editor.recursive().label(),
+                    focus == OwnershipField::Recursive,
+                ),
+                Line::styled(
+                    format!(
+                        "Currently {}; empty fields stay unchanged",
+                        editor.current()
+                    ),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]);
+
+            let candidates: Vec<Line> = editor
+                .completions()
+                .into_iter()
+                .map(|name| Line::styled(name.to_string(), Style::default().fg(Color::Gray)))
+                .collect();
+
+            f.render_widget(Clear, area);
+            f.render_widget(block, area);
+            f.render_widget(fields, sections[0]);
+            f.render_widget(
+                Paragraph::new(candidates).block(Block::default().borders(Borders::TOP)),
+                sections[1],
+            );
+        }
+
+        if let PopupType::PasteLink = &self.popup() {
+            let key = |key: &str, label: &str| {
+                Line::from(vec![
+                    Span::styled(format!(" {key}  "), Style::default().fg(Color::LightBlue)),
+                    Span::raw(label.to_string()),
+                ])
+            };
+            let title = format!("Link {} path(s) here as", self.clipboard().get_path().len());
+            let menu = Paragraph::new(vec![
+                key("s", "Symbolic link (absolute path)"),
+                key("r", "Symbolic link (relative path)"),
+                key("h", "Hard link"),
+                key("Esc", "Cancel"),
+            ])
+            .block(Block::bordered().border_type(Rounded).title(title).blue());
+
+            let area = popup_area(f.area(), 30, 20);
+
+            f.render_widget(Clear, area);
+            f.render_widget(menu, area);
+        }
+
+        if let Some(symlink) = self.new_symlink() {
+            let field = |label: &str, value: &str, focused: bool| {
+                let style = if focused {
+                    Style::default().fg(Color::Black).bg(Color::Blue)
+                } else {
+                    Style::default()
+                };
+                Line::from(vec![
+                    Span::styled(format!("{label:<8}"), Style::default().fg(Color::Gray)),
+                    Span::styled(value.to_string(), style),
+                ])
+            };
+            let focus = symlink.focus();
+            let fields = Paragraph::new(vec![
+                field("Target", symlink.target(), focus == SymlinkField::Target),
+                field("Name", symlink.name(), focus == SymlinkField::Name),
+                Line::styled(
+                    "An empty name uses the target's; relative targets stay relative",
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ])
+            .wrap(Wrap { trim: false })
+            .block(
+                Block::bordered()
+                    .border_type(Rounded)
+                    .title("New symlink - Tab: next field, Enter: create")
+                    .blue(),
+            );
+
+            let area = popup_area(f.area(), 50, 20);
+
+            f.render_widget(Clear, area);
+            f.render_widget(fields, area);
+        }
+
+        if let Some(menu) = self.open_with() {
+            let items: Vec<ListItem> = menu
+                .rules()
+                .iter()
+                .map(|rule| {
+                    let kind = if rule.terminal() {
+                        "terminal"
+                    } else {
+                        "detached"
+                    };
+                    ListItem::new(Line::from(vec![
+                        Span::raw(rule.name().to_string()),
+                        Span::styled(format!("  ({kind})"), Style::default().fg(Color::Gray)),
+                    ]))
+                })
+                .collect();
+            let title = format!("Open {} file(s) with", menu.paths().len());
+            let menu_list = List::new(items)
+                .highlight_style(Style::default().bg(Color::Blue).fg(Color::Black))
+                .block(Block::bordered().border_type(Rounded).title(title).blue());
+            let mut selection = menu.selection().clone();
+
+            let area = popup_area(f.area(), 30, 30);
+
+            f.render_widget(Clear, area);
+            f.render_stateful_widget(menu_list, area, &mut selection);
+        }
+
+        if let PopupType::Shell = &self.popup() {
+            let title = format!(
+                "Shell ({}) - Tab: switch, %f file, %s selection, %d dir",
+                self.shell_mode().label()
+            );
+            let input = self.input_buffer();
+            let input_paragraph = Paragraph::new(format!("$ {input}"))
+                .block(Block::bordered().border_type(Rounded).title(title).blue());
+
+            let area = popup_area(f.area(), 50, 10);
+
+            f.render_widget(Clear, area);
+            f.render_widget(input_paragraph, area);
+        }
+
+        if let PopupType::ShellLog = &self.popup() {
+            let area = popup_area(f.area(), 80, 70);
+            let jobs_height = u16::try_from(self.shell_jobs().len().min(5)).unwrap_or(5) + 2;
+            let sections =
+                Layout::vertical([Constraint::Length(jobs_height), Constraint::Min(1)]).split(area);
+
+            let job_items: Vec<ListItem> = self
+                .shell_jobs()
+                .iter()
+                .map(|job| {
+                    let (status, color) = match (job.running(), job.exit_code()) {
+                        (true, _) => ("running".to_string(), Color::Yellow),
+                        (false, Some(0)) => ("exit 0".to_string(), Color::Green),
+                        (false, Some(code)) => (format!("exit {code}"), Color::Red),
+                        (false, None) => ("killed".to_string(), Color::Red),
+                    };
+                    ListItem::new(Line::from(vec![
+                        Span::styled(format!("[{status}] "), Style::default().fg(color)),
+                        Span::raw(job.command().to_string()),
+                    ]))
+                })
+                .collect();
+            let jobs_list = List::new(job_items)
+                .highlight_style(Style::default().bg(Color::Blue).fg(Color::Black))
+                .block(
+                    Block::bordered()
+                        .border_type(Rounded)
+                        .title("Background commands")
+                        .blue(),
+                );
+            let mut selection = self.shell_log_selection().clone();
+
+            // Show the tail of the output that fits.
+            let output_block = Block::bordered().border_type(Rounded).title("Output");
+            let visible = usize::from(output_block.inner(sections[1]).height);
+            let output: Vec<Line> = selection
+                .selected()
+                .and_then(|index| self.shell_jobs().get(index))
+                .map(|job| {
+                    let skip = job.output().len().saturating_sub(visible);
+                    job.output()
+                        .iter()
+                        .skip(skip)
+                        .map(|line| Line::raw(line.clone()))
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            f.render_widget(Clear, area);
+            f.render_stateful_widget(jobs_list, sections[0], &mut selection);
+            f.render_widget(Paragraph::new(output).block(output_block), sections[1]);
+        }
+
         // Render notification if available
         if let Some(noti) = &self.notify() {
             let area = bottom_right_area(main_layout[1], 35, 5);
@@ -277,9 +877,21 @@ impl FileManager {
 
         let mut size_display = Span::raw("");
 
-        if let Some(entry) = self.get_selected_index_entry() {
-            if *entry.entry_type() == FsEntryType::File {
-                let size = format_size(entry.size());
+        if let Some(entry) = self.get_selected_index_entry_unmut() {
+            let size = match entry.entry_type() {
+                FsEntryType::File | FsEntryType::Executable => Some(format_size(entry.size())),
+                FsEntryType::Directory => Some(
+                    self.dir_size_label(entry.entry_path())
+                        .unwrap_or_else(|| "z to measure".to_string()),
+                ),
+                FsEntryType::Symlink
+                | FsEntryType::Fifo
+                | FsEntryType::Socket
+                | FsEntryType::BlockDevice
+                | FsEntryType::CharDevice
+                | FsEntryType::Unknown => None,
+            };
+            if let Some(size) = size {
                 size_display = Span::styled(
                     format!(" | Size: {size}"),
                     Style::default().fg(Color::LightMagenta),
@@ -302,8 +914,91 @@ impl FileManager {
             ),
         };
 
-        // Combine mode + size
-        let combined_info = Line::from(vec![mode_display, size_display]);
+        let mut filter_display = Span::raw("");
+        if let Some(filter) = self.filter() {
+            filter_display = Span::styled(
+                format!(
+                    " | Filter: {} ({}) {}/{}",
+                    filter.pattern(),
+                    filter.kind().label(),
+                    self.entries().len(),
+                    self.all_entries().len()
+                ),
+                Style::default().fg(Color::LightYellow),
+            );
+        }
+
+        let sort_display = Span::styled(
+            format!(" | Sort: {}", self.sort().label()),
+            Style::default().fg(Color::Gray),
+        );
+
+        let running_jobs = self.shell_jobs().iter().filter(|job| job.running()).count();
+        let jobs_display = if running_jobs > 0 {
+            Span::styled(
+                format!(" | Jobs: {running_jobs}"),
+                Style::default().fg(Color::Yellow),
+            )
+        } else {
+            Span::raw("")
+        };
+
+        let options = self.list_options();
+        let visibility_display = Span::styled(
+            format!(
+                " | Hidden: {}{}",
+                if options.show_hidden() {
+                    "shown"
+                } else {
+                    "off"
+                },
+                if options.respect_ignore() {
+                    " | Ignore rules: on"
+                } else {
+                    ""
+                }
+            ),
+            Style::default().fg(Color::Gray),
+        );
+
+        let git_display = match self.git() {
+            Some(git) => Span::styled(
+                format!(" | ⎇ {}", git.branch_label()),
+                Style::default().fg(Color::LightMagenta),
+            ),
+            None => Span::raw(""),
+        };
+
+        let read_only_display = if self.read_only() {
+            Span::styled(" | Read-only", Style::default().fg(Color::LightRed))
+        } else {
+            Span::raw("")
+        };
+
+        let chooser_display = match self.chooser() {
+            Some(chooser) => Span::styled(
+                if chooser.dirs_only() {
+                    " | Choose a directory: Enter (c for this one)"
+                } else {
+                    " | Choose a file: Enter"
+                },
+                Style::default().fg(Color::LightCyan),
+            ),
+            None => Span::raw(""),
+        };
+
+        // Combine mode + size + filter + sort + visibility
+        let combined_info = Line::from(vec![
+            mode_display,
+            chooser_display,
+            read_only_display,
+            size_display,
+            filter_display,
+            sort_display,
+            visibility_display,
+            git_display,
+            jobs_display,
+        ]);
 
         let mode_paragraph = Paragraph::new(combined_info)
             .block(Block::default().borders(Borders::NONE))
@@ -313,9 +1008,11 @@ impl FileManager {
 
         let mut per_display = Span::raw("");
         if let Some(entry) = self.get_selected_index_entry() {
-            let permission = entry.file_permission();
-
-            let permisson_str = mode_to_string(permission);
+            let permisson_str = if *entry.entry_type() == FsEntryType::Unknown {
+                "unknown".to_string()
+            } else {
+                format_mode(entry.file_permission())
+            };
             per_display = Span::styled(
                 format!("Permisson: {permisson_str} "),
                 Style::default().fg(Color::LightCyan),
@@ -328,4 +1025,176 @@ impl FileManager {
 
         f.render_widget(per_paragraph, bottom_layout[1]);
     }
+
+    // The analyzer reuses the three panes: the parent level, the current level with usage
+    // bars, and the children of the hovered directory.
+    fn render_analyzer(&mut self, f: &mut Frame, header: Rect, panes: &[Rect]) {
+        let Some(analyzer) = self.analyzer_mut() else {
+            return;
+        };
+        let block = Block::bordered().border_type(Rounded).borders(Borders::ALL);
+        f.render_widget(Clear, header);
+        for area in panes {
+            f.render_widget(Clear, *area);
+            f.render_widget(&block, *area);
+        }
+
+        let Some(current) = analyzer.current_node() else {
+            let scanned = analyzer
+                .scanned()
+                .load(std::sync::atomic::Ordering::Relaxed);
+            let status = format!("Scanning {}… {scanned} files", analyzer.current().display());
+            f.render_widget(Paragraph::new(status), header);
+            return;
+        };
+
+        let use_disk = analyzer.use_disk();
+        let summary = format!(
+            "Disk usage: {} | {} apparent, {} on disk, {} files | s: sort by {} | d: delete, t: trash, q: close",
+            current.path().display(),
+            format_size(current.apparent()),
+            format_size(current.disk()),
+            current.files(),
+            if use_disk { "apparent size" } else { "disk usage" },
+        );
+        let total = analyzer.size_of(current).max(1);
+        let rows: Vec<ListItem> = current
+            .children()
+            .iter()
+            .map(|child| usage_row(child, total, use_disk))
+            .collect();
+
+        let root = analyzer.root().map(|root| root.path().clone());
+        let parent_rows: Vec<ListItem> = match current.path().parent() {
+            Some(parent) if root.as_ref() != Some(current.path()) => analyzer
+                .root()
+                .and_then(|root| root.find(parent))
+                .map(|parent| {
+                    parent
+                        .children()
+                        .iter()
+                        .map(|child| compact_row(child, use_disk, child.path() == current.path()))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            _ => vec![compact_row(current, use_disk, true)],
+        };
+        let preview_rows: Vec<ListItem> = analyzer
+            .hovered_node()
+            .map(|node| {
+                node.children()
+                    .iter()
+                    .map(|child| compact_row(child, use_disk, false))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let confirm = analyzer.confirm();
+        let hovered = analyzer.hovered_node().map(|node| node.path().clone());
+
+        f.render_widget(
+            Paragraph::new(summary).style(Style::default().fg(Color::LightCyan)),
+            header,
+        );
+        f.render_widget(List::new(parent_rows), block.inner(panes[0]));
+        f.render_widget(List::new(preview_rows), block.inner(panes[2]));
+        let usage_list = List::new(rows)
+            .block(
+                Block::bordered()
+                    .border_type(Rounded)
+                    .title("     % usage        apparent       disk   files name"),
+            )
+            .highlight_style(
+                Style::default()
+                    .bg(Color::Blue)
+                    .fg(Color::Black)
+                    .add_modifier(Modifier::BOLD),
+            );
+        f.render_stateful_widget(usage_list, panes[1], analyzer.selection_mut());
+
+        if let (Some(action), Some(path)) = (confirm, hovered) {
+            let verb = match action {
+                AnalyzerAction::Delete => "Delete",
+                AnalyzerAction::Trash => "Move to trash",
+            };
+            let title = format!("{verb}? Yes(Y) No(N)");
+            let prompt = Paragraph::new(path.to_string_lossy().to_string())
+                .wrap(Wrap { trim: false })
+                .block(Block::bordered().border_type(Rounded).title(title).red());
+            let area = popup_area(f.area(), 50, 20);
+            f.render_widget(Clear, area);
+            f.render_widget(prompt, area);
+        }
+    }
+}
+
+fn node_name(node: &DuNode) -> String {
+    let name = node
+        .path()
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| node.path().to_string_lossy().to_string());
+    if node.is_dir() {
+        format!("{name}/")
+    } else {
+        name
+    }
+}
+
+fn usage_row(node: &DuNode, total: u64, use_disk: bool) -> ListItem<'static> {
+    let size = if use_disk {
+        node.disk()
+    } else {
+        node.apparent()
+    };
+    let ratio = size as f64 / total as f64;
+    let filled = ((ratio * 10.0).round() as usize).min(10);
+    let bar = format!("{}{}", "█".repeat(filled), "░".repeat(10 - filled));
+    let name_style = if node.is_dir() {
+        Style::default().fg(Color::LightBlue)
+    } else {
+        Style::default()
+    };
+    ListItem::new(Line::from(vec![
+        Span::styled(
+            format!("{:>6.1}% ", ratio * 100.0),
+            Style::default().fg(Color::Gray),
+        ),
+        Span::styled(bar, Style::default().fg(Color::Cyan)),
+        Span::raw(format!(
+            " {:>10} {:>10} {:>7} ",
+            format_size(node.apparent()),
+            format_size(node.disk()),
+            node.files()
+        )),
+        Span::styled(node_name(node), name_style),
+    ]))
+}
+
+fn compact_row(node: &DuNode, use_disk: bool, highlighted: bool) -> ListItem<'static> {
+    let size = if use_disk {
+        node.disk()
+    } else {
+        node.apparent()
+    };
+    let style = if highlighted {
+        Style::default().bg(Color::Blue).fg(Color::Black)
+    } else {
+        Style::default()
+    };
+    ListItem::new(Line::from(vec![
+        Span::styled(node_name(node), style),
+        Span::styled(
+            format!("  {}", format_size(size)),
+            Style::default().fg(Color::Gray),
+        ),
+    ]))
+}
+
+// Stands in for a listing that could not be read.
+fn unavailable<'a>(reason: &'a str, block: Block<'a>) -> Paragraph<'a> {
+    Paragraph::new(reason)
+        .alignment(Alignment::Center)
+        .style(Style::default().fg(Color::LightRed))
+        .wrap(Wrap { trim: true })
+        .block(block)
 }
@@ -1,65 +1,250 @@
+use crate::ignore_rules::IgnoreRules;
+use crate::modals::{GitInfo, ListOptions, SortMode, SortOptions};
 use crate::{FsEntry, FsEntryType};
 use mime_guess::Mime;
 use ratatui::{
     layout::{Constraint, Flex, Layout, Rect},
     style::{Color, Style},
-    text::Span,
+    text::{Line, Span},
     widgets::ListItem,
 };
 use std::cmp::Ordering;
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 
 use std::{
-    os::unix::fs::MetadataExt,
+    os::unix::fs::{DirBuilderExt, FileTypeExt, MetadataExt},
     path::{Path, PathBuf},
 };
 use tokio::{fs, io};
+use uzers::{get_current_uid, get_user_by_uid};
 
-pub async fn list_dir(p: &PathBuf) -> tokio::io::Result<Vec<FsEntry>> {
+impl FsEntryType {
+    // Links are classified as links; their target is only looked at when used.
+    pub fn from_metadata(meta: &std::fs::Metadata) -> Self {
+        let file_type = meta.file_type();
+        if file_type.is_dir() {
+            Self::Directory
+        } else if file_type.is_symlink() {
+            Self::Symlink
+        } else if file_type.is_fifo() {
+            Self::Fifo
+        } else if file_type.is_socket() {
+            Self::Socket
+        } else if file_type.is_block_device() {
+            Self::BlockDevice
+        } else if file_type.is_char_device() {
+            Self::CharDevice
+        } else if meta.mode() & 0o111 != 0 {
+            Self::Executable
+        } else {
+            Self::File
+        }
+    }
+
+    pub fn icon(&self) -> &'static str {
+        match self {
+            Self::Directory => "📁",
+            Self::File => "📄",
+            Self::Executable => "⚡",
+            Self::Symlink => "🔗",
+            Self::Fifo => "🚰",
+            Self::Socket => "🔌",
+            Self::BlockDevice => "💽",
+            Self::CharDevice => "📟",
+            Self::Unknown => "❔",
+        }
+    }
+
+    // Pipes, sockets and devices are never opened for preview.
+    pub fn special_label(&self) -> Option<&'static str> {
+        match self {
+            Self::Fifo => Some("Named pipe"),
+            Self::Socket => Some("Socket"),
+            Self::BlockDevice => Some("Block device"),
+            Self::CharDevice => Some("Character device"),
+            Self::Directory | Self::File | Self::Executable | Self::Symlink | Self::Unknown => None,
+        }
+    }
+}
+
+pub async fn list_dir(p: &PathBuf, options: &ListOptions) -> tokio::io::Result<Vec<FsEntry>> {
     let mut rd = fs::read_dir(p).await?;
     let mut items = Vec::new();
+    let ignore_rules = if options.respect_ignore() {
+        Some(IgnoreRules::load(p))
+    } else {
+        None
+    };
+    let current_uid = get_current_uid();
+    let mut owners: HashMap<u32, String> = HashMap::new();
 
     while let Some(entry) = rd.next_entry().await? {
+        if !options.show_hidden() && entry.file_name().as_encoded_bytes().starts_with(b".") {
+            continue;
+        }
         let file_path = entry.path();
-        let meta = fs::symlink_metadata(&file_path).await?;
+        // An entry whose metadata can't be read is still listed, just without details.
+        let meta = match fs::symlink_metadata(&file_path).await {
+            Ok(meta) => meta,
+            Err(_) => {
+                if !ignore_rules
+                    .as_ref()
+                    .is_some_and(|rules| rules.is_ignored(&file_path, false))
+                {
+                    items.push(FsEntry::new(
+                        entry.file_name(),
+                        file_path,
+                        FsEntryType::Unknown,
+                        0,
+                        0,
+                        false,
+                        None,
+                        None,
+                        None,
+                    ));
+                }
+                continue;
+            }
+        };
+        if ignore_rules
+            .as_ref()
+            .is_some_and(|rules| rules.is_ignored(&file_path, meta.is_dir()))
+        {
+            continue;
+        }
         let file_size = meta.size();
         let permission: u32 = meta.mode();
-        let file_type = if meta.is_dir() {
-            FsEntryType::Directory
-        } else if meta.file_type().is_symlink() {
-            FsEntryType::Symlink
-        } else {
-            FsEntryType::File
-        };
+        let file_type = FsEntryType::from_metadata(&meta);
         let mimetype = get_mime(&file_path).await;
+        let owner = (meta.uid() != current_uid).then(|| {
+            owners
+                .entry(meta.uid())
+                .or_insert_with(|| {
+                    get_user_by_uid(meta.uid()).map_or(meta.uid().to_string(), |user| {
+                        user.name().to_string_lossy().to_string()
+                    })
+                })
+                .clone()
+        });
         let item = FsEntry::new(
-            entry.file_name().into_string().unwrap(),
+            entry.file_name(),
             file_path,
             file_type,
             file_size,
             permission,
             false,
             mimetype,
+            meta.modified().ok(),
+            owner,
         );
 
         items.push(item);
     }
-    sort_entries(&mut items).await;
+    sort_entries(&mut items, options.sort());
     Ok(items)
 }
-async fn sort_entries(entries: &mut [FsEntry]) {
-    entries.sort_by(|a, b| match (a.entry_type(), b.entry_type()) {
-        (FsEntryType::Directory, FsEntryType::Directory) => Ordering::Equal,
-        (FsEntryType::Directory, FsEntryType::File) => Ordering::Less,
-        (FsEntryType::Directory, FsEntryType::Symlink) => Ordering::Less,
-        (FsEntryType::File, FsEntryType::Directory) => Ordering::Greater,
-        (FsEntryType::File, FsEntryType::File) => Ordering::Equal,
-        (FsEntryType::File, FsEntryType::Symlink) => Ordering::Less,
-        (FsEntryType::Symlink, FsEntryType::Directory) => Ordering::Greater,
-        (FsEntryType::Symlink, FsEntryType::File) => Ordering::Greater,
-        (FsEntryType::Symlink, FsEntryType::Symlink) => Ordering::Equal,
+
+fn sort_entries(entries: &mut [FsEntry], options: &SortOptions) {
+    entries.sort_by(|a, b| {
+        let order = match options.mode() {
+            SortMode::Natural => Ordering::Equal,
+            SortMode::Size => a.size().cmp(&b.size()),
+            SortMode::Modified => a.modified().cmp(&b.modified()),
+            SortMode::Extension => extension_key(a).cmp(&extension_key(b)),
+            SortMode::Type => type_rank(a.entry_type())
+                .cmp(&type_rank(b.entry_type()))
+                .then_with(|| mime_key(a).cmp(&mime_key(b))),
+        }
+        .then_with(|| natural_cmp(a.display_name(), b.display_name()));
+
+        let order = if options.reverse() {
+            order.reverse()
+        } else {
+            order
+        };
+
+        if options.dirs_first() {
+            let a_dir = *a.entry_type() == FsEntryType::Directory;
+            let b_dir = *b.entry_type() == FsEntryType::Directory;
+            b_dir.cmp(&a_dir).then(order)
+        } else {
+            order
+        }
     });
 }
+
+fn type_rank(entry_type: &FsEntryType) -> u8 {
+    match entry_type {
+        FsEntryType::Directory => 0,
+        FsEntryType::File | FsEntryType::Executable => 1,
+        FsEntryType::Symlink => 2,
+        FsEntryType::Fifo
+        | FsEntryType::Socket
+        | FsEntryType::BlockDevice
+        | FsEntryType::CharDevice => 3,
+        FsEntryType::Unknown => 4,
+    }
+}
+
+fn extension_key(entry: &FsEntry) -> String {
+    Path::new(entry.name())
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+fn mime_key(entry: &FsEntry) -> String {
+    entry
+        .mime_type()
+        .as_ref()
+        .map(|mime| mime.essence_str().to_string())
+        .unwrap_or_default()
+}
+
+// Case-insensitive comparison that orders digit runs by value, so "file2" < "file10".
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut left = a.chars().peekable();
+    let mut right = b.chars().peekable();
+
+    loop {
+        match (left.peek().copied(), right.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x_digits = take_digits(&mut left);
+                let y_digits = take_digits(&mut right);
+                let x_value = x_digits.trim_start_matches('0');
+                let y_value = y_digits.trim_start_matches('0');
+                let order = x_value
+                    .len()
+                    .cmp(&y_value.len())
+                    .then_with(|| x_value.cmp(y_value))
+                    .then_with(|| x_digits.len().cmp(&y_digits.len()));
+                if order != Ordering::Equal {
+                    return order;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let order = x.to_lowercase().cmp(y.to_lowercase());
+                if order != Ordering::Equal {
+                    return order;
+                }
+                left.next();
+                right.next();
+            }
+        }
+    }
+}
+
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        digits.push(c);
+    }
+    digits
+}
+
 pub async fn copy_dir_iterative(src: &Path, dst: &Path) -> io::Result<()> {
     let mut todo = VecDeque::new();
     todo.push_back((src.to_path_buf(), dst.to_path_buf()));
@@ -95,79 +280,189 @@ pub async fn move_file(src: &Path, dst: &Path) -> io::Result<()> {
     result
 }
 
-pub async fn get_mime(src: &Path) -> Option<Mime> {
-    mime_guess::from_path(src).first()
-}
+// Moves `path` into the freedesktop.org trash, so file managers can restore it. Files on
+// the home filesystem go to `$XDG_DATA_HOME/Trash`, others to `$topdir/.Trash-$uid` at the
+// root of their own mount; nothing is copied.
+pub fn move_to_trash(path: &Path) -> io::Result<()> {
+    let trash = trash_dir(path)?;
+    for dir in ["files", "info"] {
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(trash.join(dir))?;
+    }
 
-pub async fn read_valid_file(path: &PathBuf) -> io::Result<String> {
-    if fs::metadata(path).await?.len() == 0 {
-        Ok("Empty File".to_string())
-    } else {
-        fs::read_to_string(path).await
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "nothing to trash"))?;
+    let absolute = std::path::absolute(path)?;
+    let info = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        percent_encode(&absolute),
+        trash_timestamp()
+    );
+
+    // The `.trashinfo` file is created first, exclusively, to claim a free name.
+    for n in 1.. {
+        let mut candidate = name.to_os_string();
+        if n > 1 {
+            candidate.push(format!(".{n}"));
+        }
+        let mut info_name = candidate.clone();
+        info_name.push(".trashinfo");
+        let info_path = trash.join("info").join(info_name);
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&info_path)
+        {
+            Ok(mut file) => {
+                use std::io::Write;
+                file.write_all(info.as_bytes())?;
+                return std::fs::rename(path, trash.join("files").join(candidate)).inspect_err(
+                    |_| {
+                        let _ = std::fs::remove_file(&info_path);
+                    },
+                );
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
     }
+    unreachable!()
 }
 
-pub fn mode_to_string(mode: u32) -> String {
-    let mut result = String::new();
+fn trash_dir(path: &Path) -> io::Result<PathBuf> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no trash directory"))?;
+    let absolute = std::path::absolute(path)?;
+    let parent = absolute.parent().unwrap_or(&absolute);
+    let device = std::fs::metadata(parent)?.dev();
+    if device_of(&data_home)? == device {
+        return Ok(data_home.join("Trash"));
+    }
 
-    // Each tuple is (bitmask, char to use if bit is set)
-    let flags = [
-        (0o400, 'r'),
-        (0o200, 'w'),
-        (0o100, 'x'), // user
-        (0o040, 'r'),
-        (0o020, 'w'),
-        (0o010, 'x'), // group
-        (0o004, 'r'),
-        (0o002, 'w'),
-        (0o001, 'x'), // others
-    ];
+    // The top directory is the last ancestor still on the file's device.
+    let mut topdir = parent;
+    while let Some(up) = topdir.parent() {
+        if std::fs::metadata(up)?.dev() != device {
+            break;
+        }
+        topdir = up;
+    }
+    Ok(topdir.join(format!(".Trash-{}", get_current_uid())))
+}
 
-    for (bit, ch) in flags {
-        if mode & bit != 0 {
-            result.push(ch);
-        } else {
-            result.push('-');
+// The device of `path`, or of its nearest existing ancestor when the trash is not made yet.
+fn device_of(path: &Path) -> io::Result<u64> {
+    let mut current = path;
+    loop {
+        match std::fs::metadata(current) {
+            Ok(meta) => return Ok(meta.dev()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                current = current.parent().ok_or(e)?;
+            }
+            Err(e) => return Err(e),
         }
     }
+}
 
-    result
+fn percent_encode(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+    let mut encoded = String::new();
+    for &byte in path.as_os_str().as_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+// `YYYY-MM-DDThh:mm:ss` in local time, as the trash spec asks.
+fn trash_timestamp() -> String {
+    chrono::Local::now().format("%Y-%m-%dT%H:%M:%S").to_string()
+}
+
+pub async fn get_mime(src: &Path) -> Option<Mime> {
+    mime_guess::from_path(src).first()
+}
+
+pub async fn read_valid_file(path: &PathBuf) -> io::Result<String> {
+    let metadata = fs::metadata(path).await?;
+    // Pipes and devices can block forever or never end.
+    if !metadata.is_file() {
+        return Err(io::Error::other("Not a regular file"));
+    }
+    if metadata.len() == 0 {
+        Ok("Empty File".to_string())
+    } else {
+        fs::read_to_string(path).await
+    }
 }
 
 pub async fn get_state_data(
     start: &PathBuf,
-) -> tokio::io::Result<(Vec<FsEntry>, Option<PathBuf>, Vec<FsEntry>)> {
-    let entries = list_dir(start).await?;
+    options: &ListOptions,
+) -> tokio::io::Result<(
+    Vec<FsEntry>,
+    Option<PathBuf>,
+    tokio::io::Result<Vec<FsEntry>>,
+)> {
+    let entries = list_dir(start, options).await?;
     let parent_path = start.parent().map(|p| p.to_path_buf());
-    let parent_entries = if let Some(ref p) = parent_path {
-        list_dir(p).await?
-    } else {
-        Vec::new()
+    // The parent only decorates the view, so failing to list it doesn't stop navigation.
+    let parent_entries = match parent_path {
+        Some(ref p) => list_dir(p, options).await,
+        None => Ok(Vec::new()),
     };
     Ok((entries, parent_path, parent_entries))
 }
 
-pub fn convert_to_listitems(f: &[FsEntry]) -> Vec<ListItem> {
+// Short wording for the panes, where the full OS message rarely fits.
+pub fn listing_error(e: &std::io::Error) -> String {
+    match e.kind() {
+        std::io::ErrorKind::PermissionDenied => "permission denied".to_string(),
+        _ => e.to_string(),
+    }
+}
+
+pub fn convert_to_listitems<'a>(f: &'a [FsEntry], git: Option<&GitInfo>) -> Vec<ListItem<'a>> {
     let list_items: Vec<ListItem> = f
         .iter()
         .map(|item| {
-            let display = match item.entry_type() {
-                FsEntryType::Directory => format!("📁 {}", item.name()),
-                FsEntryType::File => format!("📄 {}", item.name()),
-                FsEntryType::Symlink => format!("🔗{}", item.name()),
-            };
+            let display = format!("{} {}", item.entry_type().icon(), item.display_name());
             let mut style = Style::default();
             if item.is_selected {
                 style = style.bg(Color::DarkGray);
             } else {
                 style = Style::default();
             }
-            ListItem::new(Span::styled(display, style))
+            ListItem::new(Line::from(vec![
+                Span::styled(display, style),
+                git_span(git, item),
+            ]))
         })
         .collect();
     list_items
 }
 
+// Git status marker shown after an entry name.
+pub fn git_span(git: Option<&GitInfo>, entry: &FsEntry) -> Span<'static> {
+    match git.and_then(|git| git.status(entry.entry_path())) {
+        Some(status) => Span::styled(
+            format!(" {}", status.symbol()),
+            Style::default().fg(status.color()),
+        ),
+        None => Span::raw(""),
+    }
+}
+
 pub fn format_size(size: u64) -> String {
     const KB: f64 = 1024.0;
     const MB: f64 = KB * 1024.0;